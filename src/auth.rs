@@ -0,0 +1,151 @@
+use crate::config::{AuthMode, Config};
+use crate::http::{HttpTransport, TokenSource, USER_AGENT, build_client, parse_headers};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::process::Command;
+use std::sync::Mutex;
+
+/// Builds the HTTP transport for the configured `--auth` mode, sending any `--header`s. Under
+/// `--auth app`, installation tokens are minted through the same client.
+pub fn build_transport(config: &Config) -> Result<HttpTransport, String> {
+    let headers = parse_headers(&config.headers)?;
+    let client = build_client(headers, std::time::Duration::from_secs(config.request_timeout))?;
+    let transport = match config.auth {
+        AuthMode::Token => resolve_token(config).map(HttpTransport::new),
+        AuthMode::App => AppTokenSource::from_config(config, client.clone()).map(HttpTransport::with_token_source),
+    }?;
+    Ok(transport.with_client(client))
+}
+
+/// Finds the token to authenticate with: `--token-file`, then `--token-command`, then the
+/// `GH_PAT` environment variable.
+pub fn resolve_token(config: &Config) -> Result<String, String> {
+    if let Some(path) = &config.token_file {
+        let token = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        return non_empty(token, &path.display().to_string());
+    }
+    if let Some(command) = &config.token_command {
+        let output = shell(command)
+            .output()
+            .map_err(|e| format!("failed to run token command: {}", e))?;
+        if !output.status.success() {
+            return Err(format!("token command exited with {}", output.status));
+        }
+        let token = String::from_utf8(output.stdout).map_err(|_| "token command printed invalid UTF-8".to_string())?;
+        return non_empty(token, "token command");
+    }
+    env::var("GH_PAT").map_err(|_| "GH_PAT not set".to_string())
+}
+
+fn non_empty(token: String, source: &str) -> Result<String, String> {
+    let token = token.trim_end_matches(['\r', '\n']).trim();
+    if token.is_empty() {
+        return Err(format!("{} produced an empty token", source));
+    }
+    Ok(token.to_string())
+}
+
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
+
+/// Renew installation tokens this long before GitHub says they expire.
+const REFRESH_MARGIN_MINUTES: i64 = 5;
+
+/// Installation tokens for a GitHub App. Each one is minted by signing a short-lived JWT
+/// with the app's private key and trading it in, and lasts an hour; a new one is minted
+/// whenever the current one is close to expiring, so long-running `serve` keeps working.
+pub struct AppTokenSource {
+    app_id: u64,
+    installation_id: u64,
+    key: EncodingKey,
+    client: reqwest::blocking::Client,
+    current: Mutex<Option<InstallationToken>>,
+}
+
+#[derive(Deserialize, Clone)]
+struct InstallationToken {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct AppClaims {
+    iat: i64,
+    exp: i64,
+    iss: String,
+}
+
+impl AppTokenSource {
+    pub fn from_config(config: &Config, client: reqwest::blocking::Client) -> Result<Self, String> {
+        let app_id = config.app_id.ok_or("--auth app needs --app-id")?;
+        let installation_id = config.installation_id.ok_or("--auth app needs --installation-id")?;
+        let path = config.app_private_key.as_ref().ok_or("--auth app needs --app-private-key")?;
+        let pem = fs::read(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let key = EncodingKey::from_rsa_pem(&pem).map_err(|e| format!("invalid private key {}: {}", path.display(), e))?;
+        Ok(AppTokenSource {
+            app_id,
+            installation_id,
+            key,
+            client,
+            current: Mutex::new(None),
+        })
+    }
+
+    fn app_jwt(&self) -> Result<String, String> {
+        // Backdated a minute to tolerate clock drift; GitHub rejects JWTs valid for over ten minutes.
+        let now = Utc::now();
+        let claims = AppClaims {
+            iat: (now - Duration::seconds(60)).timestamp(),
+            exp: (now + Duration::minutes(9)).timestamp(),
+            iss: self.app_id.to_string(),
+        };
+        jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &self.key)
+            .map_err(|e| format!("failed to sign app JWT: {}", e))
+    }
+
+    fn mint(&self) -> Result<InstallationToken, String> {
+        let url = format!("https://api.github.com/app/installations/{}/access_tokens", self.installation_id);
+        let response = self
+            .client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", self.app_jwt()?))
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", USER_AGENT)
+            .send()
+            .map_err(|e| format!("failed to mint installation token: {}", e))?;
+        let status = response.status();
+        let body = response.text().map_err(|e| format!("failed to mint installation token: {}", e))?;
+        if !status.is_success() {
+            return Err(format!("minting installation token failed with {}: {}", status, body));
+        }
+        serde_json::from_str(&body).map_err(|e| format!("unexpected installation token response: {}", e))
+    }
+}
+
+impl TokenSource for AppTokenSource {
+    fn token(&self) -> Result<String, String> {
+        let mut current = self.current.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let refresh_before = Utc::now() + Duration::minutes(REFRESH_MARGIN_MINUTES);
+        match current.as_ref() {
+            Some(token) if token.expires_at > refresh_before => Ok(token.token.clone()),
+            _ => {
+                let token = self.mint()?;
+                let value = token.token.clone();
+                *current = Some(token);
+                Ok(value)
+            }
+        }
+    }
+}
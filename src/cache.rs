@@ -0,0 +1,156 @@
+use crate::config::{CacheAction, CacheArgs, Config};
+use crate::error::Error;
+use crate::http::{Response, Transport};
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// One saved response, in `<cache dir>/<hash of the request>.json`.
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    /// The request it answers, to rule out hash collisions.
+    request: String,
+    stored_at: DateTime<Utc>,
+    response: Response,
+}
+
+/// With `--cache`, answers requests from responses saved in the last `--cache-expiry`
+/// seconds, and saves the successful ones it has to fetch. Failed responses aren't kept, so
+/// an outage doesn't outlive itself.
+pub struct CachingTransport<'a> {
+    inner: &'a dyn Transport,
+    dir: PathBuf,
+    expiry: chrono::Duration,
+}
+
+impl<'a> CachingTransport<'a> {
+    pub fn new(inner: &'a dyn Transport, config: &Config) -> Self {
+        CachingTransport {
+            inner,
+            dir: config.cache_dir(),
+            expiry: chrono::Duration::seconds(config.cache_expiry as i64),
+        }
+    }
+
+    fn entry_path(&self, request: &str) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        request.hash(&mut hasher);
+        self.dir.join(format!("{:016x}.json", hasher.finish()))
+    }
+
+    fn lookup(&self, request: &str) -> Option<Response> {
+        let entry: CacheEntry = serde_json::from_str(&fs::read_to_string(self.entry_path(request)).ok()?).ok()?;
+        let fresh = Utc::now() - entry.stored_at < self.expiry;
+        (fresh && entry.request == request).then_some(entry.response)
+    }
+
+    fn store(&self, request: &str, response: &Response) {
+        let entry = CacheEntry {
+            request: request.to_string(),
+            stored_at: Utc::now(),
+            response: response.clone(),
+        };
+        let result = fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(self.entry_path(request), serde_json::to_string(&entry).unwrap_or_default()));
+        if let Err(e) = result {
+            eprintln!("warning: couldn't write to the cache in {}: {}", self.dir.display(), e);
+        }
+    }
+
+    fn cached(
+        &self,
+        request: String,
+        fetch: impl FnOnce() -> Result<Response, Box<dyn std::error::Error>>,
+    ) -> Result<Response, Box<dyn std::error::Error>> {
+        if let Some(response) = self.lookup(&request) {
+            return Ok(response);
+        }
+        let response = fetch()?;
+        if response.is_success() {
+            self.store(&request, &response);
+        }
+        Ok(response)
+    }
+}
+
+impl Transport for CachingTransport<'_> {
+    fn get(&self, url: &str) -> Result<Response, Box<dyn std::error::Error>> {
+        self.cached(format!("GET {}", url), || self.inner.get(url))
+    }
+
+    fn post_json(&self, url: &str, body: &serde_json::Value) -> Result<Response, Box<dyn std::error::Error>> {
+        self.cached(format!("POST {} {}", url, body), || self.inner.post_json(url, body))
+    }
+}
+
+/// The files in the cache directory; a missing directory is an empty cache.
+fn entries(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let Ok(listing) = fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+    let mut paths = Vec::new();
+    for entry in listing {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+/// How many entries the cache holds and their total size in bytes.
+pub fn cache_info(dir: &Path) -> Result<(usize, u64), Error> {
+    let paths = entries(dir)?;
+    let mut bytes = 0;
+    for path in &paths {
+        bytes += fs::metadata(path)?.len();
+    }
+    Ok((paths.len(), bytes))
+}
+
+/// Removes entries stored more than `max_age` ago, and any that no longer parse. Returns how
+/// many were removed.
+pub fn prune_cache(dir: &Path, max_age: chrono::Duration, now: DateTime<Utc>) -> Result<usize, Error> {
+    let mut removed = 0;
+    for path in entries(dir)? {
+        let entry: Option<CacheEntry> = serde_json::from_str(&fs::read_to_string(&path)?).ok();
+        if entry.is_none_or(|entry| now - entry.stored_at >= max_age) {
+            fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Removes every entry. Returns how many there were.
+pub fn clear_cache(dir: &Path) -> Result<usize, Error> {
+    let paths = entries(dir)?;
+    for path in &paths {
+        fs::remove_file(path)?;
+    }
+    Ok(paths.len())
+}
+
+/// `whoami cache <prune|clear|info>`.
+pub fn run_cache_command(config: &Config, args: &CacheArgs) -> Result<(), Error> {
+    let dir = config.cache_dir();
+    match args.action {
+        CacheAction::Prune { max_age } => {
+            let max_age = chrono::Duration::seconds(max_age.unwrap_or(config.cache_expiry) as i64);
+            let removed = prune_cache(&dir, max_age, Utc::now())?;
+            println!("removed {} stale entries from {}", removed, dir.display());
+        }
+        CacheAction::Clear => {
+            let removed = clear_cache(&dir)?;
+            println!("removed {} entries from {}", removed, dir.display());
+        }
+        CacheAction::Info => {
+            let (count, bytes) = cache_info(&dir)?;
+            println!("{}: {} entries, {}", dir.display(), count, crate::render::human_bytes(bytes));
+        }
+    }
+    Ok(())
+}
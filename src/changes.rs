@@ -0,0 +1,60 @@
+use crate::config::Config;
+use crate::render::abbreviate_number;
+use crate::stats::Stats;
+use std::fs;
+use std::path::Path;
+
+/// Reads the stats saved by the previous run. A missing file means there was no previous
+/// run; one that no longer parses (say, written by an older version) is treated the same,
+/// with a warning.
+pub fn load_snapshot(path: &Path) -> Option<Stats> {
+    let text = fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&text) {
+        Ok(stats) => Some(stats),
+        Err(e) => {
+            eprintln!("warning: ignoring unreadable snapshot {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+pub fn save_snapshot(path: &Path, stats: &Stats) -> Result<(), Box<dyn std::error::Error>> {
+    fs::write(path, serde_json::to_string_pretty(stats)? + "\n")?;
+    Ok(())
+}
+
+/// A one-line commit subject naming the headline numbers that moved since `previous`, e.g.
+/// `Stats update: stars 2.3k→2.4k, prs 312→315`.
+pub fn summarize_changes(previous: Option<&Stats>, current: &Stats, config: &Config) -> String {
+    let Some(previous) = previous else {
+        return "Stats update: first snapshot".to_string();
+    };
+
+    let mut changes = Vec::new();
+    let mut compare = |name: &str, before: Option<u64>, after: Option<u64>| {
+        if let (Some(before), Some(after)) = (before, after)
+            && abbreviate_number(before, config) != abbreviate_number(after, config)
+        {
+            changes.push(format!("{} {}→{}", name, abbreviate_number(before, config), abbreviate_number(after, config)));
+        }
+    };
+    compare("stars", Some(previous.total_stars), Some(current.total_stars));
+    compare("commits", previous.total_commits_this_year, current.total_commits_this_year);
+    compare("prs", Some(previous.total_prs), Some(current.total_prs));
+    compare("issues", Some(previous.total_issues), Some(current.total_issues));
+    compare("contributed to", previous.contributed_to, current.contributed_to);
+    compare("starred", Some(previous.stars_given), Some(current.stars_given));
+
+    let top_language = |stats: &Stats| stats.languages.first().map(|(lang, _)| lang.clone());
+    if let (Some(before), Some(after)) = (top_language(previous), top_language(current))
+        && before != after
+    {
+        changes.push(format!("top language {}→{}", before, after));
+    }
+
+    if changes.is_empty() {
+        "Stats update: no visible changes".to_string()
+    } else {
+        format!("Stats update: {}", changes.join(", "))
+    }
+}
@@ -0,0 +1,193 @@
+use crate::config::Config;
+use crate::http::Transport;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Linguist's colors for the languages that turn up most, so cards can match the bars on
+/// GitHub's own repo pages.
+const LANGUAGE_COLORS: &[(&str, &str)] = &[
+    ("Assembly", "#6E4C13"),
+    ("C", "#555555"),
+    ("C#", "#178600"),
+    ("C++", "#f34b7d"),
+    ("CMake", "#DA3434"),
+    ("CSS", "#563d7c"),
+    ("Clojure", "#db5855"),
+    ("Crystal", "#000100"),
+    ("Dart", "#00B4AB"),
+    ("Dockerfile", "#384d54"),
+    ("Elixir", "#6e4a7e"),
+    ("Emacs Lisp", "#c065db"),
+    ("Erlang", "#B83998"),
+    ("F#", "#b845fc"),
+    ("Fortran", "#4d41b1"),
+    ("GLSL", "#5686a5"),
+    ("Go", "#00ADD8"),
+    ("Groovy", "#4298b8"),
+    ("HCL", "#844FBA"),
+    ("HTML", "#e34c26"),
+    ("Haskell", "#5e5086"),
+    ("Java", "#b07219"),
+    ("JavaScript", "#f1e05a"),
+    ("Julia", "#a270ba"),
+    ("Jupyter Notebook", "#DA5B0B"),
+    ("Kotlin", "#A97BFF"),
+    ("Lua", "#000080"),
+    ("MATLAB", "#e16737"),
+    ("Makefile", "#427819"),
+    ("Nim", "#ffc200"),
+    ("Nix", "#7e7eff"),
+    ("OCaml", "#ef7a08"),
+    ("Objective-C", "#438eff"),
+    ("PHP", "#4F5D95"),
+    ("Perl", "#0298c3"),
+    ("PowerShell", "#012456"),
+    ("Python", "#3572A5"),
+    ("R", "#198CE7"),
+    ("Ruby", "#701516"),
+    ("Rust", "#dea584"),
+    ("SCSS", "#c6538c"),
+    ("Scala", "#c22d40"),
+    ("Shell", "#89e051"),
+    ("Solidity", "#AA6746"),
+    ("Svelte", "#ff3e00"),
+    ("Swift", "#F05138"),
+    ("TeX", "#3D6117"),
+    ("TypeScript", "#3178c6"),
+    ("Vim Script", "#199f4b"),
+    ("Visual Basic .NET", "#945db7"),
+    ("Vue", "#41b883"),
+    ("WebAssembly", "#04133b"),
+    ("Zig", "#ec915c"),
+];
+
+/// What languages missing from the table are drawn in.
+pub const FALLBACK_COLOR: &str = "#858585";
+
+/// Where Linguist keeps every language's color, for `--remote-colors`.
+pub const LINGUIST_LANGUAGES_URL: &str = "https://raw.githubusercontent.com/github-linguist/linguist/main/lib/linguist/languages.yml";
+
+/// Tries at fetching `LINGUIST_LANGUAGES_URL` before settling for an older copy.
+const FETCH_ATTEMPTS: usize = 2;
+
+/// The colors `load_remote_colors` found, consulted before the built-in table.
+static REMOTE_COLORS: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// The Linguist color for a language, by its Linguist name: from `--remote-colors` when
+/// they loaded, else the built-in table.
+pub fn language_color(name: &str) -> Option<&'static str> {
+    if let Some(color) = REMOTE_COLORS.get().and_then(|colors| colors.get(name)) {
+        return Some(color);
+    }
+    LANGUAGE_COLORS
+        .iter()
+        .find(|(language, _)| *language == name)
+        .map(|(_, color)| *color)
+}
+
+/// Where `load_remote_colors` got the colors from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColorSource {
+    Fetched,
+    /// The copy the last successful fetch left in the cache.
+    Cached(PathBuf),
+    /// The table compiled in; nothing newer could be had.
+    BuiltIn,
+}
+
+/// Fetches Linguist's `languages.yml` for `--remote-colors` and keeps a copy in the cache
+/// directory. When the fetch fails, the cached copy stands in, then the built-in table, so a
+/// color hiccup never fails the run; which one was used is logged.
+pub fn load_remote_colors(http: &dyn Transport, config: &Config) -> ColorSource {
+    let cached = config.cache_dir().join("languages.yml");
+    let mut problem = String::new();
+    for _ in 0..FETCH_ATTEMPTS {
+        match fetch_linguist_colors(http) {
+            Ok((body, colors)) => {
+                if let Err(e) = fs::create_dir_all(config.cache_dir()).and_then(|_| fs::write(&cached, body)) {
+                    eprintln!("warning: failed to cache the Linguist colors at {}: {}", cached.display(), e);
+                }
+                let _ = REMOTE_COLORS.set(colors);
+                eprintln!("using the current Linguist colors from {}", LINGUIST_LANGUAGES_URL);
+                return ColorSource::Fetched;
+            }
+            Err(e) => problem = e,
+        }
+    }
+    let colors = fs::read_to_string(&cached).ok().map(|text| parse_linguist_colors(&text)).filter(|colors| !colors.is_empty());
+    match colors {
+        Some(colors) => {
+            let _ = REMOTE_COLORS.set(colors);
+            eprintln!("warning: failed to fetch the Linguist colors ({}); using the copy cached at {}", problem, cached.display());
+            ColorSource::Cached(cached)
+        }
+        None => {
+            eprintln!("warning: failed to fetch the Linguist colors ({}); using the built-in ones", problem);
+            ColorSource::BuiltIn
+        }
+    }
+}
+
+fn fetch_linguist_colors(http: &dyn Transport) -> Result<(String, HashMap<String, String>), String> {
+    let response = http.get(LINGUIST_LANGUAGES_URL).map_err(|e| e.to_string())?;
+    if !response.is_success() {
+        return Err(format!("HTTP {}", response.status));
+    }
+    let colors = parse_linguist_colors(&response.body);
+    if colors.is_empty() {
+        return Err("no colors in the response".to_string());
+    }
+    Ok((response.body, colors))
+}
+
+/// The `color` of each language in Linguist's `languages.yml`. Only the two levels that
+/// matter are read (a top-level `Name:` and its indented `color: "#rrggbb"`), so no YAML
+/// parser is needed.
+pub fn parse_linguist_colors(yaml: &str) -> HashMap<String, String> {
+    let mut colors = HashMap::new();
+    let mut language: Option<&str> = None;
+    for line in yaml.lines() {
+        if line.starts_with([' ', '#', '-']) || line.is_empty() {
+            let color = line.strip_prefix("  color:").map(|color| color.trim().trim_matches(['"', '\'']));
+            if let (Some(language), Some(color)) = (language, color) {
+                colors.insert(language.to_string(), color.to_string());
+            }
+        } else {
+            language = line.strip_suffix(':').map(|name| name.trim_matches(['"', '\'']));
+        }
+    }
+    colors
+}
+
+/// The channel levels of xterm's 6×6×6 color cube (indexes 16–231).
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The closest of the 256 terminal colors to a `#rrggbb` color, picking between the color
+/// cube and the grayscale ramp (232–255). `None` if `hex` doesn't parse.
+pub fn nearest_ansi256(hex: &str) -> Option<u8> {
+    let hex = hex.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    let rgb = [channel(0)?, channel(2)?, channel(4)?];
+    let distance = |candidate: [u8; 3]| -> u32 {
+        rgb.iter().zip(candidate).map(|(&a, b)| (a as i32 - b as i32).pow(2) as u32).sum()
+    };
+
+    let nearest_level = |value: u8| {
+        (0..CUBE_LEVELS.len()).min_by_key(|&i| (CUBE_LEVELS[i] as i32 - value as i32).abs()).unwrap_or(0)
+    };
+    let [r, g, b] = rgb.map(nearest_level);
+    let cube = (16 + 36 * r + 6 * g + b) as u8;
+    let cube_rgb = [CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]];
+
+    let average = rgb.iter().map(|&c| c as u32).sum::<u32>() / 3;
+    let step = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray_level = 8 + 10 * step;
+    let gray = 232 + step;
+
+    Some(if distance([gray_level; 3]) < distance(cube_rgb) { gray } else { cube })
+}
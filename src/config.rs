@@ -0,0 +1,1117 @@
+use crate::oneline::DEFAULT_ONELINE_FORMAT;
+use chrono::prelude::*;
+use clap::builder::Resettable;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Regenerates the profile README from live GitHub stats")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub config: Config,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Serve the stats card over HTTP instead of writing README.md.
+    Serve(ServeArgs),
+    /// Print the version and build information.
+    Version,
+    /// Check that the token, templates and config are usable, without rendering anything.
+    Doctor,
+    /// Inspect or trim the `--cache` directory.
+    Cache(CacheArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct CacheArgs {
+    #[command(subcommand)]
+    pub action: CacheAction,
+}
+
+#[derive(Subcommand, Debug, Clone, Copy)]
+pub enum CacheAction {
+    /// Remove entries older than `--cache-expiry`, or `--max-age`.
+    Prune {
+        /// Seconds an entry may be old and stay.
+        #[arg(long)]
+        max_age: Option<u64>,
+    },
+    /// Remove every entry.
+    Clear,
+    /// Print where the cache is, how many entries it holds and their size.
+    Info,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ServeArgs {
+    /// Address to listen on.
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    pub bind: String,
+
+    /// Seconds to reuse gathered stats before asking GitHub again.
+    #[arg(long, default_value_t = 600)]
+    pub cache_ttl: u64,
+}
+
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Format {
+    /// The profile README, rendered from `README.md.tera`.
+    Markdown,
+    /// The stats card, rendered from `stats.svg.tera`.
+    Svg,
+    /// The SVG stats card rasterized with the bundled fonts.
+    Png,
+    /// Prometheus text exposition of the raw numbers.
+    Prometheus,
+    /// OpenMetrics samples with timestamps: one per `--history-file` line, then this run's.
+    Openmetrics,
+    /// A single compact line for shell prompts and status bars, see `--oneline-format`.
+    Oneline,
+    /// A shields.io endpoint badge for the stat picked with `--badge`.
+    Shields,
+    /// A page rendered from the `--template-file` or `--template-string`, HTML-escaped. None
+    /// is bundled.
+    Html,
+    /// Every gathered stat, unabbreviated, as JSON; see `--json-pretty`. A `.json.tera`
+    /// `--template-file` shapes it instead.
+    Json,
+}
+
+impl Format {
+    pub fn default_output(self) -> &'static str {
+        match self {
+            Format::Markdown => "README.md",
+            Format::Svg => "stats.svg",
+            Format::Png => "stats.png",
+            Format::Html => "stats.html",
+            Format::Prometheus | Format::Openmetrics | Format::Oneline | Format::Shields | Format::Json => "-",
+        }
+    }
+
+    /// Whether the output is text, which `--bom` and `--line-endings` apply to.
+    pub fn is_text(self) -> bool {
+        self != Format::Png
+    }
+
+    /// The bundled template this format renders, if it renders one at all.
+    pub fn template(self) -> Option<&'static str> {
+        match self {
+            Format::Markdown => Some("README.md.tera"),
+            Format::Svg | Format::Png => Some("stats.svg.tera"),
+            Format::Html | Format::Prometheus | Format::Openmetrics | Format::Oneline | Format::Shields | Format::Json => None,
+        }
+    }
+
+    /// Whether a `--template-file` can stand in for what this format renders.
+    pub fn takes_template(self) -> bool {
+        self.template().is_some() || matches!(self, Format::Html | Format::Json)
+    }
+
+    /// The format a template renders, going by its extension: `.md.tera` is Markdown,
+    /// `.svg.tera` SVG, `.html.tera` HTML and `.json.tera` JSON. Other templates imply none.
+    pub fn for_template(path: &Path) -> Option<Format> {
+        let name = path.file_name()?.to_str()?;
+        if name.ends_with(".md.tera") {
+            Some(Format::Markdown)
+        } else if name.ends_with(".svg.tera") {
+            Some(Format::Svg)
+        } else if name.ends_with(".html.tera") {
+            Some(Format::Html)
+        } else if name.ends_with(".json.tera") {
+            Some(Format::Json)
+        } else {
+            None
+        }
+    }
+
+    fn name(self) -> String {
+        self.to_possible_value().expect("no skipped variants").get_name().to_string()
+    }
+}
+
+/// The line endings text output is written with.
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LineEndings {
+    Lf,
+    Crlf,
+}
+
+/// The stat a `--format shields` badge shows.
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Badge {
+    Stars,
+    Commits,
+    Prs,
+    /// The top language, in its Linguist color.
+    TopLanguage,
+}
+
+/// Which of the user's repos count toward stars and languages.
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Visibility {
+    Public,
+    Private,
+    All,
+}
+
+impl Visibility {
+    /// The GraphQL `RepositoryPrivacy` value, or `None` for no filter.
+    pub fn graphql_privacy(self) -> Option<&'static str> {
+        match self {
+            Visibility::Public => Some("PUBLIC"),
+            Visibility::Private => Some("PRIVATE"),
+            Visibility::All => None,
+        }
+    }
+
+    /// The REST `visibility` query value, or `None` for no filter.
+    pub fn rest_visibility(self) -> Option<&'static str> {
+        match self {
+            Visibility::Public => Some("public"),
+            Visibility::Private => Some("private"),
+            Visibility::All => None,
+        }
+    }
+}
+
+/// Which relationship to a repo makes it count toward the language chart.
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReposType {
+    /// Repos the user owns.
+    Owner,
+    /// Repos the user collaborates on or reaches through an organization.
+    Member,
+    /// Both of the above.
+    All,
+}
+
+impl ReposType {
+    /// The REST `type` query value.
+    pub fn rest_type(self) -> &'static str {
+        match self {
+            ReposType::Owner => "owner",
+            ReposType::Member => "member",
+            ReposType::All => "all",
+        }
+    }
+
+    /// The same selection spelled as `affiliation`, which unlike `type` may be combined with
+    /// `visibility`.
+    pub fn rest_affiliation(self) -> &'static str {
+        match self {
+            ReposType::Owner => "owner",
+            ReposType::Member => "collaborator,organization_member",
+            ReposType::All => "owner,collaborator,organization_member",
+        }
+    }
+}
+
+/// How requests to GitHub are authenticated.
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuthMode {
+    /// A personal access token from `--token-file`, `--token-command` or `GH_PAT`.
+    Token,
+    /// A GitHub App installation token, minted from `--app-id`, `--installation-id` and
+    /// `--app-private-key` and renewed before it expires.
+    App,
+}
+
+/// Whether commit counts include restricted contributions: `restrictedContributionsCount`,
+/// the user's private contributions the token can't see. Those it can see are counted either
+/// way.
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CountRestricted {
+    /// Only if the token's scopes include `repo`.
+    Auto,
+    Always,
+    Never,
+}
+
+/// Where `total_commits_this_year` comes from.
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CommitMode {
+    /// GitHub's commit contributions: commits you authored, public plus restricted (see
+    /// `--count-restricted`).
+    Contributions,
+    /// Every commit on the default branch of your own non-fork repos, whoever wrote it,
+    /// minus those by `--exclude-authors`.
+    DefaultBranch,
+}
+
+/// The period the "this year" counts cover.
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Window {
+    /// January 1st through December 31st of the current year.
+    CalendarYear,
+    /// The 365 days up to now, so the counts don't reset every January.
+    #[value(name = "rolling-365")]
+    #[serde(rename = "rolling-365")]
+    Rolling365,
+}
+
+impl Window {
+    /// The window's bounds around `now`, with days starting at midnight in `offset`. Both
+    /// fit GraphQL's one-year limit on `contributionsCollection`.
+    pub fn range(self, now: DateTime<Utc>, offset: FixedOffset) -> (DateTime<FixedOffset>, DateTime<FixedOffset>) {
+        let now = now.with_timezone(&offset);
+        match self {
+            Window::CalendarYear => (
+                offset.with_ymd_and_hms(now.year(), 1, 1, 0, 0, 0).unwrap(),
+                offset.with_ymd_and_hms(now.year(), 12, 31, 23, 59, 59).unwrap(),
+            ),
+            Window::Rolling365 => (now - chrono::Duration::days(365), now),
+        }
+    }
+
+    /// How a template might phrase the window, e.g. "in 2025".
+    pub fn describe(self, now: DateTime<Utc>, offset: FixedOffset) -> String {
+        match self {
+            Window::CalendarYear => format!("in {}", now.with_timezone(&offset).year()),
+            Window::Rolling365 => "in the last 365 days".to_string(),
+        }
+    }
+}
+
+/// A costly part of the main GraphQL query that `--skip-field` can leave out.
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkipField {
+    /// The day-by-day calendar behind `weekday_distribution` and `contribution_grid`.
+    ContributionCalendar,
+    /// The owned repos, 100 per page, behind the star total and everything derived from it.
+    Repositories,
+    /// The repos contributed to, behind `contributed_to` and `contributed_repos`.
+    ContributedRepos,
+    PinnedItems,
+    Organizations,
+}
+
+/// What the language chart's shares are of.
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LangMetric {
+    /// Bytes of code, summed across repos.
+    Bytes,
+    /// Repos, each counted once for its primary language.
+    RepoCount,
+}
+
+/// Whose repos the language chart is drawn from.
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LangFrom {
+    /// Your repos, as `--repos-type` lists them, whenever they were written.
+    Owned,
+    /// The repos you committed to within `--window`, whoever owns them.
+    Contributions,
+}
+
+/// What a full progress bar stands for.
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum BarScale {
+    /// A full bar is 100% of all code.
+    Absolute,
+    /// A full bar is the largest language shown, and the rest are proportional to it.
+    Relative,
+}
+
+/// Which end of a progress bar fills first.
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum BarDirection {
+    /// Filled cells on the left.
+    Ltr,
+    /// Filled cells on the right, for right-to-left layouts.
+    Rtl,
+}
+
+/// A group of stats `--only` can ask for.
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum StatCategory {
+    /// Stars across your repos, the influence score and their disk usage.
+    Stars,
+    /// Commit counts, the all-time breakdown and the weekday distribution.
+    Commits,
+    Prs,
+    Issues,
+    /// The repos you contributed to.
+    Contributions,
+    /// The repos you starred.
+    Starred,
+    /// Language shares and the top language; the most expensive stage, one request per repo.
+    Languages,
+}
+
+impl StatCategory {
+    /// The categories answered by the main GraphQL query.
+    pub const GRAPHQL: [StatCategory; 6] = [
+        StatCategory::Stars,
+        StatCategory::Commits,
+        StatCategory::Prs,
+        StatCategory::Issues,
+        StatCategory::Contributions,
+        StatCategory::Starred,
+    ];
+
+    /// The template flag saying whether the category was gathered, e.g. `has_stars`.
+    pub fn flag(self) -> String {
+        format!("has_{}", self.to_possible_value().expect("no skipped variants").get_name())
+    }
+}
+
+/// Every setting that shapes a run. Each one can come from a flag or, spelled the same in
+/// kebab-case, from the TOML file passed with `--config`; flags win over the file.
+#[derive(Parser, Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// TOML file to read settings from.
+    #[arg(long)]
+    #[serde(skip)]
+    pub config: Option<PathBuf>,
+
+    /// Authenticate with a personal access token or as a GitHub App installation.
+    #[arg(long, value_enum, default_value_t = AuthMode::Token)]
+    pub auth: AuthMode,
+
+    /// The GitHub App's ID, for `--auth app`.
+    #[arg(long)]
+    pub app_id: Option<u64>,
+
+    /// The installation of the app to mint tokens for, for `--auth app`.
+    #[arg(long)]
+    pub installation_id: Option<u64>,
+
+    /// PEM file holding the app's private key, for `--auth app`.
+    #[arg(long)]
+    pub app_private_key: Option<PathBuf>,
+
+    /// Read the GitHub token from this file instead of `GH_PAT`.
+    #[arg(long, conflicts_with = "token_command")]
+    pub token_file: Option<PathBuf>,
+
+    /// Run this shell command and use its stdout as the GitHub token (e.g. `pass show github`).
+    #[arg(long)]
+    pub token_command: Option<String>,
+
+    /// Send this header, as `Name: Value`, with every GitHub request, for proxies and API
+    /// gateways that want their own. Repeatable. `Authorization` stays the token's.
+    #[arg(long = "header", value_name = "NAME: VALUE")]
+    #[serde(default)]
+    pub headers: Vec<String>,
+
+    /// Give up on a single GitHub request after this many seconds. A timed-out request
+    /// fails like any other: the repo's languages are left out with a warning (or fail the
+    /// run under `--strict`), and a GraphQL query falls back to REST. Nothing is retried,
+    /// except a search after waiting out its rate limit.
+    #[arg(long, value_name = "SECONDS", default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..))]
+    pub request_timeout: u64,
+
+    /// Abort the whole run, however far it got, after this many seconds. Once the output,
+    /// snapshot and history are being written, they finish instead. Doesn't apply to `serve`
+    /// or `--tui`. No deadline by default.
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub deadline: Option<u64>,
+
+    /// What to render. Defaults to what the `--template-file` extension implies, or Markdown.
+    #[arg(long, value_enum, default_value_t = Format::Markdown)]
+    pub format: Format,
+
+    /// Render this template instead of the one the format bundles. `card.svg.tera` renders
+    /// (and escapes, and validates) as SVG without `--format svg`; `.md.tera` as Markdown,
+    /// `.html.tera` as HTML and `.json.tera` as JSON. Any other extension needs a `--format`.
+    #[arg(long, conflicts_with = "template_string")]
+    pub template_file: Option<PathBuf>,
+
+    /// Render this template text instead, for one-offs like `--template-string "⭐ {{ total_stars }}"`.
+    /// It sees the full context and the custom filters, and nothing in it is escaped.
+    #[arg(long, value_name = "TEMPLATE")]
+    pub template_string: Option<String>,
+
+    /// Preview the stats in the terminal instead of writing anything; `r` refetches, `q` quits.
+    #[arg(long)]
+    pub tui: bool,
+
+    /// Where to write the rendered output, `-` for stdout; defaults to a file named after the
+    /// format, or stdout for the machine-readable formats and `--template-string`.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// Layout for `--format oneline`. Placeholders: {user} {stars} {stars_given} {commits}
+    /// {commits_all_time} {prs} {prs_year} {issues} {issues_year} {contributed} {top_language}.
+    #[arg(long, default_value = DEFAULT_ONELINE_FORMAT)]
+    pub oneline_format: String,
+
+    /// Insert values into SVG and HTML templates verbatim instead of HTML-escaping them, for
+    /// templates that build markup out of the values themselves.
+    #[arg(long)]
+    pub no_autoescape: bool,
+
+    /// Save GitHub's responses under `--cache-dir` and reuse them for `--cache-expiry`
+    /// seconds, so reruns while editing a template don't spend API calls.
+    #[arg(long)]
+    pub cache: bool,
+
+    /// For debugging: write the body of every response from GitHub, GraphQL and REST, to a
+    /// numbered file in this directory. Tokens are redacted; private repo names aren't.
+    #[arg(long, value_name = "DIR")]
+    pub dump_responses: Option<PathBuf>,
+
+    /// Color languages as Linguist does today rather than as the built-in table has it,
+    /// fetching its `languages.yml` into `--cache-dir`. A failed fetch falls back to the last
+    /// fetched copy, then the built-in colors.
+    #[arg(long)]
+    pub remote_colors: bool,
+
+    /// Where `--cache` keeps responses. Defaults to `$XDG_CACHE_HOME/whoami`, or
+    /// `~/.cache/whoami`.
+    #[arg(long)]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Seconds a cached response stays fresh.
+    #[arg(long, default_value_t = 3600)]
+    pub cache_expiry: u64,
+
+    /// Directories searched for templates before the built-in ones, in the order given:
+    /// each template name resolves from the first directory that has it, so a shared library
+    /// can come last and per-profile overrides first. Repeatable.
+    #[arg(long = "template-dir", value_name = "DIR")]
+    #[serde(default)]
+    pub template_dirs: Vec<PathBuf>,
+
+    /// Report the variables each template references but the code doesn't provide, and the
+    /// ones it provides but no template uses, then exit; nonzero if any are undefined.
+    #[arg(long)]
+    pub template_lint: bool,
+
+    /// Print the settings in effect, after merging `--config` and the flags, as TOML and exit.
+    #[arg(long)]
+    #[serde(skip)]
+    pub config_print: bool,
+
+    /// Start text output with a UTF-8 byte order mark, for Windows tools that expect one.
+    #[arg(long)]
+    pub bom: bool,
+
+    /// Write text output with these line endings, whatever the template uses.
+    #[arg(long, value_enum, default_value_t = LineEndings::Lf)]
+    pub line_endings: LineEndings,
+
+    /// Fail when the output, as written, comes to more than this many bytes, saying how many
+    /// it came to. Profile READMEs get cut short in some of GitHub's views.
+    #[arg(long, value_name = "N")]
+    pub max_output_bytes: Option<usize>,
+
+    /// Instead of failing over `--max-output-bytes`, drop the smallest language and render
+    /// again until the output fits, or the chart is empty.
+    #[arg(long, requires = "max_output_bytes")]
+    pub fit_output: bool,
+
+    /// Check the rendered SVG or Markdown is well-formed before writing it, and fail if not.
+    #[arg(long)]
+    pub validate: bool,
+
+    /// Indent `--format json` output for reading. The default when writing to a file.
+    #[arg(long, conflicts_with = "json_compact")]
+    pub json_pretty: bool,
+
+    /// Write `--format json` output on a single line, for embedding. The default on stdout.
+    #[arg(long)]
+    pub json_compact: bool,
+
+    /// The stat `--format shields` makes a badge of.
+    #[arg(long, value_enum, default_value_t = Badge::Stars)]
+    pub badge: Badge,
+
+    /// Write a commit message summarizing what changed since the last run to this file, for
+    /// `git commit -F`.
+    #[arg(long)]
+    pub emit_commit_message: Option<PathBuf>,
+
+    /// Where the stats of the last run are kept to compare against.
+    #[arg(long, default_value = ".whoami-snapshot.json")]
+    pub snapshot: PathBuf,
+
+    /// Append this run's totals and top languages as a line of JSON to this file, building a
+    /// time series of your stats.
+    #[arg(long)]
+    pub history_file: Option<PathBuf>,
+
+    /// Count the commits made since the last `--history-file` entry, as
+    /// `commits_since_last_run`. Costs a GraphQL call; skipped on the first run.
+    #[arg(long, requires = "history_file")]
+    pub since_last_run: bool,
+
+    /// Fail, writing nothing, when a `--regression-guard` stat fell by more than
+    /// `--regression-threshold` since the last `--history-file` entry: a run that reads 0
+    /// stars off a narrowed token shouldn't get committed. The first run passes.
+    #[arg(long, requires = "history_file")]
+    pub fail_on_regression: bool,
+
+    /// The drop, in percent of the last run's value, that `--fail-on-regression` fails on.
+    #[arg(long, value_name = "PERCENT", default_value_t = 50.0, value_parser = parse_percentage)]
+    pub regression_threshold: f64,
+
+    /// The stats `--fail-on-regression` watches, comma-separated. The commits aren't compared
+    /// across a new year of `--window calendar-year`, when they start over.
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "stars,commits", value_name = "CATEGORIES")]
+    pub regression_guard: Vec<StatCategory>,
+
+    /// Pixels per SVG unit when rasterizing with `--format png`.
+    #[arg(long, default_value_t = 2.0)]
+    pub png_scale: f32,
+
+    /// Pin the generation time (RFC 3339) instead of reading the clock; falls back to `SOURCE_DATE_EPOCH`.
+    #[arg(long, value_parser = parse_rfc3339)]
+    pub now: Option<DateTime<Utc>>,
+
+    /// Count commits, PRs and issues up to the end of this day (`2022-12-31`) instead of
+    /// now, for a card of how things stood then. GitHub has no history for stars, repos,
+    /// languages or the all-time PR and issue totals, so those stay current.
+    #[arg(long, value_name = "DATE")]
+    pub as_of: Option<NaiveDate>,
+
+    /// Count commit contributions or default-branch commits.
+    #[arg(long, value_enum, default_value_t = CommitMode::Contributions)]
+    pub commit_mode: CommitMode,
+
+    /// Whether commit contribution counts add the private contributions the token can't see.
+    /// `auto` checks the token's scopes first, at the cost of one request.
+    #[arg(long, value_enum, default_value_t = CountRestricted::Always)]
+    pub count_restricted: CountRestricted,
+
+    /// Logins or emails whose commits `--commit-mode default-branch` leaves out, such as
+    /// `dependabot[bot]` or `29139614+renovate[bot]@users.noreply.github.com`. Comma-separated.
+    #[arg(long, value_delimiter = ',')]
+    #[serde(default)]
+    pub exclude_authors: Vec<String>,
+
+    /// Only fetch these stat categories, comma-separated, and skip the API calls for the rest.
+    /// Templates only see the variables of the listed categories, so the bundled ones need
+    /// every category; guard yours with `has_<category>`. Default: all of them.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    #[serde(default)]
+    pub only: Vec<StatCategory>,
+
+    /// Leave this part out of the GraphQL query to spend fewer points, and its variables out
+    /// of the templates. Repeatable or comma-separated.
+    #[arg(long = "skip-field", value_enum, value_delimiter = ',', value_name = "FIELD")]
+    #[serde(default)]
+    pub skip_fields: Vec<SkipField>,
+
+    /// The period `total_commits_this_year`, `prs_this_year` and `issues_this_year` cover.
+    #[arg(long, value_enum, default_value_t = Window::CalendarYear)]
+    pub window: Window,
+
+    /// Your time zone as a UTC offset (e.g. `+02:00`), so the window and the contribution
+    /// calendar's days (and with them `weekday_distribution`) start at your midnight.
+    #[arg(long, default_value = "+00:00", value_parser = parse_utc_offset)]
+    #[serde(with = "utc_offset")]
+    pub utc_offset: FixedOffset,
+
+    /// How many pages of the REST repo listing to fetch at once when GitHub reports the page count.
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..))]
+    pub max_concurrent_pages: u16,
+
+    /// Which repos feed the language chart. `member` and `all` can pull in every repo of every
+    /// organization you belong to, each costing one languages request against the rate limit.
+    #[arg(long, value_enum, default_value_t = ReposType::Owner)]
+    pub repos_type: ReposType,
+
+    /// Stop listing repos after this many, keeping the most recently pushed. Unlimited by default.
+    #[arg(long)]
+    pub max_repos: Option<usize>,
+
+    /// Count exactly these `owner/repo`s toward the language chart and stars instead of
+    /// listing your repos, for a curated set that may include other owners'. Comma-separated;
+    /// adds to `--repos-file`.
+    #[arg(long = "repo", value_name = "OWNER/REPO", value_delimiter = ',')]
+    #[serde(default)]
+    pub repos: Vec<String>,
+
+    /// A file of `owner/repo` names, one per line (`#` starts a comment), counted as `--repo`.
+    #[arg(long)]
+    pub repos_file: Option<PathBuf>,
+
+    /// Leave these repos out of the language chart, by name or `owner/repo`. Comma-separated.
+    #[arg(long = "exclude-repo", value_name = "REPO", value_delimiter = ',')]
+    #[serde(default)]
+    pub exclude_repos: Vec<String>,
+
+    /// Leave repos whose `owner/repo` matches this regex out of the language chart too, for a
+    /// naming convention like `/legacy-`. Unanchored; repeatable.
+    #[arg(long = "exclude-repo-pattern", value_name = "REGEX", value_parser = parse_regex)]
+    #[serde(default, deserialize_with = "regexes::deserialize")]
+    pub exclude_repo_patterns: Vec<String>,
+
+    /// Byte adjustments to a repo's languages, for what Linguist misdetects (a huge generated
+    /// doc counting as HTML). Config file only, a table per repo, by name or `owner/repo`:
+    ///
+    /// ```toml
+    /// [language-overrides."octocat/site"]
+    /// HTML = -2_000_000
+    /// Markdown = 2_000_000
+    /// ```
+    ///
+    /// Applied to each repo's bytes as fetched, before `--lang-recency-halflife`,
+    /// `--min-lang-bytes` and adding up the repos. A language can't go below zero bytes, and
+    /// one at zero drops out.
+    #[arg(skip)]
+    #[serde(default)]
+    pub language_overrides: BTreeMap<String, BTreeMap<String, i64>>,
+
+    /// Estimate `estimated_lines`, the lines of code in the charted repos, from their bytes
+    /// and a typical line length per language. A rough figure: comments, blank lines and
+    /// style all move it. Needs `--lang-metric bytes`.
+    #[arg(long)]
+    pub estimate_lines: bool,
+
+    /// The bytes per line `--estimate-lines` assumes, by language, over the built-in ones.
+    /// Config file only: `[bytes-per-line]` then `Rust = 30`, say.
+    #[arg(skip)]
+    #[serde(default)]
+    pub bytes_per_line: BTreeMap<String, f64>,
+
+    /// Fold the languages past the top eight into one last `Other` entry, so the chart still
+    /// adds up to 100%.
+    #[arg(long)]
+    pub lang_other: bool,
+
+    /// Fail unless the charted language percentages add up to 100%, give or take 0.5. A check
+    /// on the language pipeline; past eight languages it needs `--lang-other`.
+    #[arg(long)]
+    pub strict_languages_sum: bool,
+
+    /// Chart languages by bytes of code, or by how many repos each is the primary language
+    /// of. `repo-count` skips repos without one, and `--min-lang-bytes`.
+    #[arg(long, value_enum, default_value_t = LangMetric::Bytes)]
+    pub lang_metric: LangMetric,
+
+    /// Chart the languages of your own repos, or of the (up to 100) repos you committed to
+    /// within `--window`. `contributions` follows what you worked on lately, including other
+    /// people's projects, but counts each repo's whole codebase, not the lines you wrote.
+    /// It needs GraphQL; the REST fallback charts your own repos.
+    #[arg(long, value_enum, default_value_t = LangFrom::Owned)]
+    pub lang_from: LangFrom,
+
+    /// Weight each repo's language bytes by how recently it was pushed, halving every this
+    /// many days, so the chart favours what you write now. No decay by default.
+    #[arg(long, value_parser = parse_positive_days)]
+    pub lang_recency_halflife: Option<f64>,
+
+    /// Show counts below this in full, and abbreviate the rest to `1.2k` or `3.4M`. Set it
+    /// high to never abbreviate. Applies to every output.
+    #[arg(long, value_name = "N", default_value_t = 1000)]
+    pub abbreviate_threshold: u64,
+
+    /// Separate the thousands of counts shown in full with commas, as in `12,345`.
+    #[arg(long)]
+    pub group_digits: bool,
+
+    /// Decimal places in each language's `percentage_str`.
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u8).range(0..=6))]
+    pub lang_precision: u8,
+
+    /// Leave out languages with fewer bytes than this across all repos, like the stray shell
+    /// script every repo carries.
+    #[arg(long, default_value_t = 0)]
+    pub min_lang_bytes: u64,
+
+    /// Scale language bars against 100% or against the top language.
+    #[arg(long, value_enum, default_value_t = BarScale::Absolute)]
+    pub bar_scale: BarScale,
+
+    /// Fill language bars from the left or, for Arabic or Hebrew layouts, from the right.
+    #[arg(long, value_enum, default_value_t = BarDirection::Ltr)]
+    pub bar_direction: BarDirection,
+
+    /// Color the filled part of each language bar with the nearest 256-color ANSI code to
+    /// its language color. Only applies when writing to a terminal, unless `--force-color`.
+    #[arg(long)]
+    pub ansi_color: bool,
+
+    /// Keep `--ansi-color` codes even when the output isn't a terminal.
+    #[arg(long, requires = "ansi_color")]
+    pub force_color: bool,
+
+    /// Cells in the `stacked_bar` the languages share; their widths always add up to this.
+    #[arg(long, default_value_t = 30)]
+    pub stacked_bar_length: usize,
+
+    /// How many of your own repos, most-starred first, to list as `top_repos`.
+    #[arg(long, default_value_t = 5)]
+    pub top_repos: usize,
+
+    /// How many of the repos you contributed to (most-starred first) to list as `contributed_repos`.
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub contributed_repos: u8,
+
+    /// How many of the organizations you're a member of to list as `organizations`. Only
+    /// public memberships show unless the token has the `read:org` scope.
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub organizations: u8,
+
+    /// Don't count your own stars on your own repos toward `total_stars`.
+    #[arg(long)]
+    pub exclude_self_stars: bool,
+
+    /// Let private repos count toward the language chart. The token sees them whenever it has
+    /// the scope, so they're left out unless asked for; `--visibility private` asks for them.
+    #[arg(long)]
+    pub include_private_langs: bool,
+
+    /// Restrict both the star sum and the language chart to public or private repos.
+    #[arg(long, value_enum, default_value_t = Visibility::All)]
+    pub visibility: Visibility,
+
+    /// Fetch this scalar GraphQL `User` field too (e.g. `location`, `isHireable`) and expose
+    /// it as `extra.<field>`. Repeatable.
+    #[arg(long = "extra-field")]
+    #[serde(default)]
+    pub extra_fields: Vec<String>,
+
+    /// How much each star counts toward `influence_score`.
+    #[arg(long, default_value_t = 1.0)]
+    pub influence_star_weight: f64,
+
+    /// How much each fork counts toward `influence_score`.
+    #[arg(long, default_value_t = 2.0)]
+    pub influence_fork_weight: f64,
+
+    /// Days since a repo's last push after which its `influence_score` contribution halves.
+    #[arg(long, default_value_t = 365.0, value_parser = parse_positive_days)]
+    pub influence_halflife: f64,
+
+    /// Also count the pull requests and issues you opened in repos you don't own, as
+    /// `external_prs` and `external_issues`. Costs two calls against the search rate limit.
+    #[arg(long)]
+    pub external_contributions: bool,
+
+    /// Also chart the languages of the repos you contributed to, as `contributed_languages`.
+    /// Costs a GraphQL call per 100 of them.
+    #[arg(long)]
+    pub contributed_languages: bool,
+
+    /// Also fetch this user's totals and expose them, with deltas against yours, as `compare`.
+    /// Roughly doubles the GraphQL calls.
+    #[arg(long)]
+    pub compare_user: Option<String>,
+
+    /// Render this user instead when yours comes back with nothing to show: no commits, PRs,
+    /// issues, stars or languages. For demo cards that must never be blank; `fallback_used`
+    /// says it happened. The language chart still comes from the token's own repos.
+    #[arg(long, value_name = "USER")]
+    pub fallback_user: Option<String>,
+
+    /// Fail on any GraphQL error instead of continuing with partial data or falling back to
+    /// REST, and on two templates under the same name.
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Also sum commits over every year since the account was created (one query per year).
+    #[arg(long)]
+    pub all_time: bool,
+
+    /// In all-time mode, leave years with fewer commits than this out of `years_active`.
+    #[arg(long, default_value_t = 1, requires = "all_time")]
+    pub min_year_commits: u64,
+
+    /// Also drop the commits of years below `--min-year-commits` from the all-time total.
+    #[arg(long, requires = "all_time")]
+    pub exclude_inactive_years: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::parse_from(["whoami"])
+    }
+}
+
+impl Config {
+    /// The `--cache-dir`, or the platform's cache location. Shared by the cached run and the
+    /// `cache` subcommand.
+    pub fn cache_dir(&self) -> PathBuf {
+        if let Some(dir) = &self.cache_dir {
+            return dir.clone();
+        }
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")));
+        match base {
+            Some(base) => base.join("whoami"),
+            None => PathBuf::from(".whoami-cache"),
+        }
+    }
+
+    /// The moment the windowed counts run up to: the last second of the `--as-of` day at
+    /// `--utc-offset`, or `now` without one.
+    pub fn counts_until(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self.as_of {
+            Some(date) => self.utc_offset.from_local_datetime(&date.and_hms_opt(23, 59, 59).unwrap()).unwrap().to_utc(),
+            None => now,
+        }
+    }
+
+    /// The window's bounds for a run at `now`, ending with the `--as-of` day if there is one
+    /// rather than the end of its year.
+    pub fn window_range(&self, now: DateTime<Utc>) -> (DateTime<FixedOffset>, DateTime<FixedOffset>) {
+        let until = self.counts_until(now);
+        let (from, to) = self.window.range(until, self.utc_offset);
+        match self.as_of {
+            Some(_) => (from, to.min(until.with_timezone(&self.utc_offset))),
+            None => (from, to),
+        }
+    }
+
+    /// How a template might phrase the window the counts cover, like "in 2025", or "in the
+    /// 365 days to 2022-12-31" with `--as-of`.
+    pub fn describe_window(&self, now: DateTime<Utc>) -> String {
+        match (self.window, self.as_of) {
+            (Window::Rolling365, Some(date)) => format!("in the 365 days to {}", date),
+            (window, _) => window.describe(self.counts_until(now), self.utc_offset),
+        }
+    }
+
+    /// The `--output`, or where the format writes by default.
+    pub fn output_path(&self) -> PathBuf {
+        match &self.output {
+            Some(output) => output.clone(),
+            None if self.template_string.is_some() => PathBuf::from("-"),
+            None => PathBuf::from(self.format.default_output()),
+        }
+    }
+
+    /// Whether `--format json` is indented: as asked, or else when it goes to a file.
+    pub fn json_is_pretty(&self) -> bool {
+        self.json_pretty || (!self.json_compact && self.output_path().as_os_str() != "-")
+    }
+
+    /// Whether `--skip-field` leaves `field` in the query.
+    pub fn queries(&self, field: SkipField) -> bool {
+        !self.skip_fields.contains(&field)
+    }
+
+    /// Whether `--only` leaves `category` in.
+    pub fn wants(&self, category: StatCategory) -> bool {
+        self.only.is_empty() || self.only.contains(&category)
+    }
+
+    /// The settings as a `--config` file would spell them. The token never lives in `Config`,
+    /// but anything shaped like one, say inside a `--token-command`, is redacted.
+    pub fn to_toml(&self) -> Result<String, String> {
+        let mut table = toml::Table::try_from(self).map_err(|e| e.to_string())?;
+        table.iter_mut().for_each(|(_, value)| redact_tokens(value));
+        toml::to_string(&table).map_err(|e| e.to_string())
+    }
+
+    /// Fills in settings from the `--config` file, if one was given. Values passed on the
+    /// command line keep priority; clap's defaults only apply to keys the file leaves out.
+    pub fn with_file(self, matches: &ArgMatches) -> Result<Config, String> {
+        let format_given = matches.value_source("format") == Some(ValueSource::CommandLine);
+        let Some(path) = self.config.clone() else {
+            return self.with_template_format(format_given);
+        };
+        let text = fs::read_to_string(&path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let file: toml::Table = text.parse().map_err(|e| config_error(&path, e))?;
+        let format_given = format_given || file.contains_key("format");
+
+        let command = Config::command();
+        let mut merged = toml::Table::try_from(&self).map_err(|e| config_error(&path, e))?;
+        for (key, value) in file {
+            let id = key.replace('-', "_");
+            let from_cli = matches.ids().any(|known| known.as_str() == id)
+                && matches.value_source(&id) == Some(ValueSource::CommandLine);
+            if !from_cli {
+                check_file_value(&command, &key, &value).map_err(|e| config_error(&path, e))?;
+                merged.insert(key, value);
+            }
+        }
+
+        let mut config: Config = merged.try_into().map_err(|e| config_error(&path, e))?;
+        config.config = Some(path);
+        config.with_template_format(format_given)
+    }
+
+    /// Takes the format from the `--template-file` extension unless one was given, in which
+    /// case the two have to agree (PNG rasterizes an SVG template, so it agrees with one).
+    /// Other extensions need a `--format`, and it has to be one that renders a template.
+    /// HTML bundles no template, so it needs one given.
+    fn with_template_format(mut self, format_given: bool) -> Result<Config, String> {
+        let Some(path) = self.template_file.as_deref() else {
+            if self.format == Format::Html && self.template_string.is_none() {
+                return Err("--format html bundles no template; pass --template-file or --template-string".to_string());
+            }
+            return Ok(self);
+        };
+        if format_given && !self.format.takes_template() {
+            return Err(format!(
+                "--format {} renders no template, so --template-file {} would go unused",
+                self.format.name(),
+                path.display()
+            ));
+        }
+        let Some(inferred) = Format::for_template(path) else {
+            if !format_given {
+                return Err(format!(
+                    "--template-file {} renders no format by its extension (.md.tera, .svg.tera, .html.tera or .json.tera); pass --format",
+                    path.display()
+                ));
+            }
+            return Ok(self);
+        };
+        if !format_given {
+            self.format = inferred;
+        } else if self.format != inferred && !(self.format == Format::Png && inferred == Format::Svg) {
+            return Err(format!(
+                "--template-file {} renders {} output, but --format is {}",
+                path.display(),
+                inferred.name(),
+                self.format.name()
+            ));
+        }
+        Ok(self)
+    }
+
+    /// The name the `--template-file` is loaded and rendered under: its file name, so the
+    /// extension still decides escaping.
+    pub fn template_file_name(&self) -> Option<String> {
+        Some(self.template_file.as_deref()?.file_name()?.to_string_lossy().into_owned())
+    }
+}
+
+/// GitHub's token prefixes: personal, OAuth, user-to-server, server-to-server, refresh and
+/// fine-grained.
+pub(crate) const TOKEN_PREFIXES: &[&str] = &["ghp_", "gho_", "ghu_", "ghs_", "ghr_", "github_pat_"];
+
+fn redact_tokens(value: &mut toml::Value) {
+    match value {
+        toml::Value::String(text) => {
+            let words: Vec<&str> = text
+                .split(' ')
+                .map(|word| if TOKEN_PREFIXES.iter().any(|prefix| word.contains(prefix)) { "<redacted>" } else { word })
+                .collect();
+            *text = words.join(" ");
+        }
+        toml::Value::Array(values) => values.iter_mut().for_each(redact_tokens),
+        toml::Value::Table(table) => table.iter_mut().for_each(|(_, value)| redact_tokens(value)),
+        _ => {}
+    }
+}
+
+/// Runs a value from the config file through its flag's value parser, so the file can't set
+/// what the flag would reject. Keys without a flag, like `language-overrides`, are left to
+/// serde.
+fn check_file_value(command: &clap::Command, key: &str, value: &toml::Value) -> Result<(), String> {
+    let id = key.replace('-', "_");
+    let Some(arg) = command.get_arguments().find(|arg| arg.get_id() == id.as_str()) else {
+        return Ok(());
+    };
+    // Switches take no value to parse; serde already checks they're booleans.
+    let Some(long) = arg.get_long().filter(|_| arg.get_action().takes_values()) else {
+        return Ok(());
+    };
+    // The flag on its own, without what it requires or conflicts with.
+    let alone = clap::Command::new("whoami").no_binary_name(true).arg(arg.clone().requires(Resettable::Reset).conflicts_with(Resettable::Reset));
+    let values: Vec<&toml::Value> = match value {
+        toml::Value::Array(values) => values.iter().collect(),
+        value => vec![value],
+    };
+    for value in values {
+        let text = match value {
+            toml::Value::String(text) => text.clone(),
+            toml::Value::Array(_) | toml::Value::Table(_) => continue,
+            value => value.to_string(),
+        };
+        if let Err(e) = alone.clone().try_get_matches_from([OsStr::new(&format!("--{}", long)), OsStr::new(&text)]) {
+            // The parser's own message, without clap's `error: invalid value ... for --flag`.
+            let reason = match std::error::Error::source(&e) {
+                Some(source) => source.to_string(),
+                None => e.to_string().lines().next().unwrap_or_default().trim_start_matches("error: ").to_string(),
+            };
+            return Err(format!("{} = {}: {}", key, value, reason));
+        }
+    }
+    Ok(())
+}
+
+fn config_error(path: &Path, e: impl std::fmt::Display) -> String {
+    format!("invalid config {}: {}", path.display(), e.to_string().trim_end())
+}
+
+fn parse_positive_days(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(days) if days > 0.0 && days.is_finite() => Ok(days),
+        _ => Err("expected a positive number of days".to_string()),
+    }
+}
+
+fn parse_percentage(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(percent),
+        _ => Err("expected a percentage from 0 to 100".to_string()),
+    }
+}
+
+fn parse_utc_offset(s: &str) -> Result<FixedOffset, String> {
+    s.parse().map_err(|_| format!("expected an offset like +02:00, got {:?}", s))
+}
+
+/// `FixedOffset` as its `+02:00` spelling in the config file.
+mod utc_offset {
+    use chrono::FixedOffset;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(offset: &FixedOffset, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(offset)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<FixedOffset, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        super::parse_utc_offset(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Checks the regex compiles, so a typo fails at startup rather than mid-run.
+fn parse_regex(s: &str) -> Result<String, String> {
+    regex::Regex::new(s).map(|_| s.to_string()).map_err(|e| e.to_string())
+}
+
+/// Regexes from the config file, checked like `parse_regex` checks the flags.
+mod regexes {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer};
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+        let patterns = Vec::<String>::deserialize(deserializer)?;
+        for pattern in &patterns {
+            super::parse_regex(pattern).map_err(D::Error::custom)?;
+        }
+        Ok(patterns)
+    }
+}
+
+fn parse_rfc3339(s: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,71 @@
+use crate::config::{AuthMode, Config};
+use crate::github::{check_installation, check_token};
+use crate::http::Transport;
+use crate::render::load_templates;
+use tera::Tera;
+
+/// Runs every check and prints a pass/fail line for each. Returns whether all of them passed.
+pub fn run_doctor(
+    http: Result<&dyn Transport, String>,
+    config: &Result<Config, String>,
+    template_glob: &str,
+) -> bool {
+    let checks = [
+        ("token", check_token_works(http, config)),
+        ("templates", check_templates(config, template_glob)),
+        ("config", check_config(config)),
+    ];
+
+    let mut healthy = true;
+    for (name, result) in &checks {
+        match result {
+            Ok(detail) => println!("[ok]   {}: {}", name, detail),
+            Err(problem) => {
+                healthy = false;
+                println!("[FAIL] {}: {}", name, problem);
+            }
+        }
+    }
+    healthy
+}
+
+fn check_token_works(http: Result<&dyn Transport, String>, config: &Result<Config, String>) -> Result<String, String> {
+    let http = http?;
+    if config.as_ref().is_ok_and(|config| config.auth == AuthMode::App) {
+        let repos = check_installation(http).map_err(|e| e.to_string())?;
+        return Ok(format!("authenticated as an app installation ({} repos)", repos));
+    }
+    let token = check_token(http).map_err(|e| e.to_string())?;
+    Ok(match token.scopes {
+        Some(scopes) if scopes.is_empty() => format!("authenticated as {} (no scopes)", token.login),
+        Some(scopes) => format!("authenticated as {} (scopes: {})", token.login, scopes.join(", ")),
+        None => format!("authenticated as {} (fine-grained token)", token.login),
+    })
+}
+
+fn check_templates(config: &Result<Config, String>, template_glob: &str) -> Result<String, String> {
+    let tera = match config {
+        Ok(config) => load_templates(template_glob, config),
+        Err(_) => Tera::new(template_glob),
+    }
+    .map_err(|e| format!("{:?}", e))?;
+    let wanted = match config {
+        Ok(config) => match config.template_file_name().or_else(|| config.format.template().map(String::from)) {
+            Some(template) => template,
+            None => return Ok(format!("{:?} output doesn't use a template", config.format)),
+        },
+        Err(_) => "README.md.tera".to_string(),
+    };
+    if !tera.get_template_names().any(|name| name == wanted) {
+        return Err(format!("{} not found under {}", wanted, template_glob));
+    }
+    Ok(format!("{} compiled ({} templates loaded)", wanted, tera.get_template_names().count()))
+}
+
+fn check_config(config: &Result<Config, String>) -> Result<String, String> {
+    let config = config.as_ref().map_err(Clone::clone)?;
+    Ok(match &config.config {
+        Some(path) => format!("{} parsed", path.display()),
+        None => "no config file, using flags and defaults".to_string(),
+    })
+}
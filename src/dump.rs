@@ -0,0 +1,68 @@
+use crate::config::TOKEN_PREFIXES;
+use crate::http::{Response, Transport};
+use regex::Regex;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Anything shaped like a GitHub token, wherever a response echoes one.
+static TOKEN: LazyLock<Regex> = LazyLock::new(|| {
+    let prefixes: Vec<String> = TOKEN_PREFIXES.iter().map(|prefix| regex::escape(prefix)).collect();
+    Regex::new(&format!("(?:{})[A-Za-z0-9_]+", prefixes.join("|"))).unwrap()
+});
+
+/// With `--dump-responses`, writes the body of every response the run gets to a file in
+/// `dir`, numbered in the order they came back and named after the request:
+/// `003-graphql-CommitLanguages.json`, `007-repos-octocat-engine-languages.json`. A debugging
+/// aid; tokens in the bodies are redacted, but private repo names and stats aren't.
+pub struct DumpingTransport<'a> {
+    inner: &'a dyn Transport,
+    dir: PathBuf,
+    count: AtomicUsize,
+}
+
+impl<'a> DumpingTransport<'a> {
+    pub fn new(inner: &'a dyn Transport, dir: PathBuf) -> Self {
+        DumpingTransport { inner, dir, count: AtomicUsize::new(0) }
+    }
+
+    fn dump(&self, name: &str, response: &Response) {
+        let number = self.count.fetch_add(1, Ordering::SeqCst) + 1;
+        let path = self.dir.join(format!("{:03}-{}.json", number, name));
+        let body = TOKEN.replace_all(&response.body, "<redacted>");
+        if let Err(e) = fs::create_dir_all(&self.dir).and_then(|_| fs::write(&path, body.as_bytes())) {
+            eprintln!("warning: couldn't dump a response to {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// `https://api.github.com/repos/octocat/engine/languages?x=1` as `repos-octocat-engine-languages`.
+fn file_name(url: &str) -> String {
+    let path = url.split_once('?').map_or(url, |(path, _)| path);
+    let path = path.split_once("://").map_or(path, |(_, rest)| rest);
+    let path = path.split_once('/').map_or("", |(_, rest)| rest);
+    let name: String = path.chars().map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '_' { c } else { '-' }).collect();
+    if name.is_empty() { "response".to_string() } else { name }
+}
+
+impl Transport for DumpingTransport<'_> {
+    fn get(&self, url: &str) -> Result<Response, Box<dyn std::error::Error>> {
+        let response = self.inner.get(url)?;
+        self.dump(&file_name(url), &response);
+        Ok(response)
+    }
+
+    fn post_json(&self, url: &str, body: &serde_json::Value) -> Result<Response, Box<dyn std::error::Error>> {
+        let response = self.inner.post_json(url, body)?;
+        let operation = body["query"]
+            .as_str()
+            .and_then(|query| query.split_once("query ")?.1.split(|c: char| !c.is_alphanumeric()).next())
+            .filter(|name| !name.is_empty());
+        match operation {
+            Some(operation) => self.dump(&format!("{}-{}", file_name(url), operation), &response),
+            None => self.dump(&file_name(url), &response),
+        }
+        Ok(response)
+    }
+}
@@ -0,0 +1,48 @@
+use chrono::prelude::*;
+
+/// Everything that can go wrong gathering and rendering stats, by kind, so callers can tell a
+/// rejected token from an outage without parsing messages.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The request didn't go through, or GitHub answered with an HTTP error. From the GraphQL
+    /// endpoint, the one kind of failure worth retrying over REST.
+    #[error("{0}")]
+    Http(String),
+    /// GitHub answered the GraphQL query with errors.
+    #[error("GraphQL query failed: {}", .0.join("; "))]
+    GraphQl(Vec<String>),
+    /// The token is missing or GitHub rejected it.
+    #[error("{0}")]
+    Auth(String),
+    #[error("GitHub rate limit exhausted until {reset}")]
+    RateLimited { reset: DateTime<Utc> },
+    #[error("GitHub has no user or org {0:?}")]
+    UserNotFound(String),
+    #[error(transparent)]
+    Template(#[from] tera::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// A setting that can't work, like an unknown `--extra-field`.
+    #[error("{0}")]
+    Config(String),
+    /// GitHub answered with JSON of an unexpected shape.
+    #[error("unexpected response from GitHub: {0}")]
+    Json(#[from] serde_json::Error),
+    /// Writing the stats out as JSON failed, which is on our side rather than GitHub's.
+    #[error("failed to encode the stats as JSON: {0}")]
+    Encode(serde_json::Error),
+    /// A stat fell further since the last run than `--fail-on-regression` allows.
+    #[error("{0}")]
+    Regression(String),
+    /// Rasterizing the card failed.
+    #[error("{0}")]
+    Raster(String),
+}
+
+/// `Transport` implementations report failures as boxed errors; all of them are failed
+/// requests.
+impl From<Box<dyn std::error::Error>> for Error {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        Error::Http(e.to_string())
+    }
+}
@@ -0,0 +1,994 @@
+use crate::config::{Config, SkipField, Visibility};
+use crate::error::Error;
+use crate::http::Transport;
+use crate::languages::{RepoLanguages, RepoSelection, apply_language_overrides, fetch_repos, last_page_from_link, listed_repos};
+use chrono::prelude::*;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::collections::HashMap;
+
+pub const GRAPHQL_URL: &str = "https://api.github.com/graphql";
+pub const VIEWER_URL: &str = "https://api.github.com/user";
+pub const INSTALLATION_REPOS_URL: &str = "https://api.github.com/installation/repositories";
+
+/// Who a token authenticates as, and the classic OAuth scopes it carries. Fine-grained tokens
+/// don't report scopes, so those come back as `None`.
+#[derive(Debug, Clone)]
+pub struct TokenInfo {
+    pub login: String,
+    pub scopes: Option<Vec<String>>,
+}
+
+/// The cheapest authenticated call GitHub offers; fails if the token is rejected.
+pub fn check_token(http: &dyn Transport) -> Result<TokenInfo, Error> {
+    let response = http.get(VIEWER_URL)?;
+    if !response.is_success() {
+        return Err(Error::Auth(format!("GitHub rejected the token ({}): {}", response.status, response.body)));
+    }
+    let viewer: serde_json::Value = response.json()?;
+    let login = viewer["login"]
+        .as_str()
+        .ok_or_else(|| Error::Auth("GitHub returned no login for the token".to_string()))?
+        .to_string();
+    let scopes = response.header("x-oauth-scopes").map(|scopes| {
+        scopes
+            .split(',')
+            .map(str::trim)
+            .filter(|scope| !scope.is_empty())
+            .map(String::from)
+            .collect()
+    });
+    Ok(TokenInfo { login, scopes })
+}
+
+/// `check_token` for a GitHub App installation token, which `/user` rejects. Returns how many
+/// repos the installation can see.
+pub fn check_installation(http: &dyn Transport) -> Result<u64, Error> {
+    let response = http.get(&format!("{}?per_page=1", INSTALLATION_REPOS_URL))?;
+    if !response.is_success() {
+        return Err(Error::Auth(format!("GitHub rejected the installation token ({}): {}", response.status, response.body)));
+    }
+    let listing: serde_json::Value = response.json()?;
+    listing["total_count"]
+        .as_u64()
+        .ok_or_else(|| Error::Auth("GitHub returned no repo count for the installation".to_string()))
+}
+
+#[derive(Deserialize, Debug)]
+struct GraphQLResponse {
+    data: Option<serde_json::Value>,
+    errors: Option<Vec<GraphQLError>>,
+}
+#[derive(Deserialize, Debug)]
+struct GraphQLError {
+    message: String,
+    #[serde(default)]
+    path: Vec<serde_json::Value>,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+}
+
+impl GraphQLError {
+    /// Errors scoped to a field below the root the query reads (`user`, or for a query without
+    /// one, each aliased field) leave the rest of the response usable. Anything above it — a
+    /// bad query, a missing user, rate limiting — does not.
+    fn is_fatal(&self, depth: usize) -> bool {
+        self.path.len() <= depth || matches!(self.kind.as_deref(), Some("RATE_LIMITED" | "FORBIDDEN"))
+    }
+
+    fn describe(&self) -> String {
+        let path: Vec<String> = self
+            .path
+            .iter()
+            .map(|segment| match segment {
+                serde_json::Value::String(field) => field.clone(),
+                other => other.to_string(),
+            })
+            .collect();
+        if path.is_empty() {
+            self.message.clone()
+        } else {
+            format!("{} (at {})", self.message, path.join("."))
+        }
+    }
+}
+#[derive(Deserialize, Debug, Default)]
+pub struct User {
+    #[serde(rename = "contributionsCollection")]
+    pub contributions_collection: ContributionsCollection,
+    #[serde(rename = "pullRequests")]
+    pub pull_requests: TotalCount,
+    pub issues: TotalCount,
+    #[serde(default)]
+    pub repositories: Repositories,
+    #[serde(rename = "repositoriesContributedTo", default)]
+    pub repositories_contributed_to: ContributedRepositories,
+    #[serde(rename = "starredRepositories")]
+    pub starred_repositories: TotalCount,
+    #[serde(rename = "pinnedItems", default)]
+    pub pinned_items: PinnedItems,
+    #[serde(default)]
+    pub organizations: Organizations,
+    /// `createdAt`, aliased so an `--extra-field createdAt` still lands in `extra`.
+    #[serde(rename = "accountCreatedAt", default)]
+    pub account_created_at: Option<DateTime<Utc>>,
+    /// The `--extra-field`s, keyed by their GraphQL names.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Scalar `User` fields that may be requested with `--extra-field`. Anything else could
+/// need a selection set or arguments and break the query.
+const EXTRA_USER_FIELDS: &[&str] = &[
+    "avatarUrl",
+    "bio",
+    "company",
+    "createdAt",
+    "email",
+    "isBountyHunter",
+    "isCampusExpert",
+    "isDeveloperProgramMember",
+    "isEmployee",
+    "isGitHubStar",
+    "isHireable",
+    "isSiteAdmin",
+    "location",
+    "login",
+    "name",
+    "pronouns",
+    "twitterUsername",
+    "updatedAt",
+    "url",
+    "websiteUrl",
+];
+
+fn extra_field_selection(fields: &[String]) -> Result<String, Error> {
+    if let Some(field) = fields.iter().find(|field| !EXTRA_USER_FIELDS.contains(&field.as_str())) {
+        return Err(Error::Config(format!(
+            "unsupported --extra-field {:?}; expected one of: {}",
+            field,
+            EXTRA_USER_FIELDS.join(", ")
+        )));
+    }
+    Ok(fields.join(" "))
+}
+#[derive(Deserialize, Debug, Default)]
+pub struct ContributionsCollection {
+    #[serde(rename = "totalCommitContributions")]
+    pub total_commit_contributions: u64,
+    #[serde(rename = "restrictedContributionsCount")]
+    pub restricted_contributions_count: u64,
+    #[serde(rename = "totalIssueContributions")]
+    pub total_issue_contributions: u64,
+    #[serde(rename = "totalPullRequestContributions")]
+    pub total_pull_request_contributions: u64,
+    /// Distinct repos committed to in the window. Only requested by `query_user_stats`.
+    #[serde(rename = "totalRepositoriesWithContributedCommits", default)]
+    pub total_repositories_with_contributed_commits: u64,
+    /// Only requested by `query_user_stats`.
+    #[serde(rename = "contributionCalendar", default)]
+    pub contribution_calendar: Option<ContributionCalendar>,
+}
+
+impl ContributionsCollection {
+    /// Commit contributions, plus the restricted (private) ones if `include_restricted`.
+    pub fn commits(&self, include_restricted: bool) -> u64 {
+        let restricted = if include_restricted { self.restricted_contributions_count } else { 0 };
+        self.total_commit_contributions + restricted
+    }
+}
+#[derive(Deserialize, Debug)]
+pub struct ContributionCalendar {
+    pub weeks: Vec<ContributionWeek>,
+}
+#[derive(Deserialize, Debug)]
+pub struct ContributionWeek {
+    #[serde(rename = "contributionDays")]
+    pub contribution_days: Vec<ContributionDay>,
+}
+#[derive(Deserialize, Debug)]
+pub struct ContributionDay {
+    pub date: NaiveDate,
+    #[serde(rename = "contributionCount")]
+    pub contribution_count: u64,
+}
+#[derive(Deserialize, Debug, Default)]
+pub struct TotalCount {
+    #[serde(rename = "totalCount")]
+    pub total_count: u64,
+}
+#[derive(Deserialize, Debug, Default)]
+pub struct Repositories {
+    /// Entries are null when GitHub couldn't resolve that repo but answered the rest.
+    pub nodes: Vec<Option<Stargazer>>,
+    #[serde(rename = "pageInfo", default)]
+    pub page_info: PageInfo,
+}
+#[derive(Deserialize, Debug, Default)]
+pub struct PageInfo {
+    #[serde(rename = "hasNextPage")]
+    pub has_next_page: bool,
+    #[serde(rename = "endCursor")]
+    pub end_cursor: Option<String>,
+}
+#[derive(Deserialize, Debug, Default)]
+pub struct ContributedRepositories {
+    #[serde(rename = "totalCount")]
+    pub total_count: u64,
+    pub nodes: Vec<Option<ContributedRepository>>,
+}
+#[derive(Deserialize, Debug)]
+pub struct ContributedRepository {
+    #[serde(rename = "nameWithOwner")]
+    pub name_with_owner: String,
+    #[serde(rename = "stargazerCount")]
+    pub stargazer_count: u64,
+}
+#[derive(Deserialize, Debug, Default)]
+pub struct PinnedItems {
+    pub nodes: Vec<Option<PinnedRepository>>,
+}
+#[derive(Deserialize, Debug)]
+pub struct PinnedRepository {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(rename = "stargazerCount")]
+    pub stargazer_count: u64,
+    #[serde(rename = "primaryLanguage")]
+    pub primary_language: Option<PrimaryLanguage>,
+}
+#[derive(Deserialize, Debug, Default)]
+pub struct Organizations {
+    pub nodes: Vec<Option<Organization>>,
+}
+#[derive(Deserialize, Debug)]
+pub struct Organization {
+    pub login: String,
+    #[serde(rename = "avatarUrl")]
+    pub avatar_url: String,
+}
+#[derive(Deserialize, Debug)]
+pub struct PrimaryLanguage {
+    pub name: String,
+    /// Null for languages Linguist has no color for, and when not asked for.
+    #[serde(default)]
+    pub color: Option<String>,
+}
+#[derive(Deserialize, Debug)]
+pub struct Stargazer {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(rename = "stargazerCount")]
+    pub stargazer_count: u64,
+    /// Whether the token's owner starred this repo, which for your own token means a self-star.
+    #[serde(rename = "viewerHasStarred", default)]
+    pub viewer_has_starred: bool,
+    #[serde(rename = "forkCount", default)]
+    pub fork_count: u64,
+    #[serde(rename = "pushedAt", default)]
+    pub pushed_at: Option<DateTime<Utc>>,
+    /// In kilobytes.
+    #[serde(rename = "diskUsage", default)]
+    pub disk_usage: Option<u64>,
+    #[serde(rename = "primaryLanguage", default)]
+    pub primary_language: Option<PrimaryLanguage>,
+}
+
+/// The fields of each owned repo node, shared by the first page and the ones after it.
+const REPOSITORY_NODE_FIELDS: &str =
+    "name description stargazerCount viewerHasStarred forkCount pushedAt diskUsage primaryLanguage { name color }";
+
+#[derive(Deserialize, Debug)]
+struct RepositoryPage {
+    repositories: Repositories,
+}
+
+/// Follows `repositories` past the 100 nodes of its first page, so every owned repo counts.
+fn fetch_remaining_repositories(
+    http: &dyn Transport,
+    username: &str,
+    config: &Config,
+    repositories: &mut Repositories,
+) -> Result<(), Error> {
+    while repositories.page_info.has_next_page {
+        let Some(cursor) = repositories.page_info.end_cursor.take() else {
+            break;
+        };
+        let query = r#"
+            query RepositoryPage($username: String!, $privacy: RepositoryPrivacy, $cursor: String!) {
+              user(login: $username) {
+                repositories(first: 100, after: $cursor, ownerAffiliations: OWNER, isFork: false, privacy: $privacy) {
+                  nodes { NODE_FIELDS }
+                  pageInfo { hasNextPage endCursor }
+                }
+              }
+            }
+        "#
+        .replace("NODE_FIELDS", REPOSITORY_NODE_FIELDS);
+        let variables = json!({
+            "username": username,
+            "privacy": config.visibility.graphql_privacy(),
+            "cursor": cursor,
+        });
+        let page: RepositoryPage = graphql(http, config, &query, variables)?;
+        repositories.nodes.extend(page.repositories.nodes);
+        repositories.page_info = page.repositories.page_info;
+    }
+    Ok(())
+}
+
+#[derive(Deserialize, Debug)]
+struct ContributedLanguagesPage {
+    #[serde(rename = "repositoriesContributedTo")]
+    repositories_contributed_to: ContributedLanguageRepositories,
+}
+#[derive(Deserialize, Debug)]
+struct ContributedLanguageRepositories {
+    nodes: Vec<Option<ContributedLanguageRepository>>,
+    #[serde(rename = "pageInfo")]
+    page_info: PageInfo,
+}
+#[derive(Deserialize, Debug)]
+struct ContributedLanguageRepository {
+    #[serde(rename = "nameWithOwner", default)]
+    name_with_owner: String,
+    #[serde(rename = "createdAt")]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(rename = "pushedAt")]
+    pushed_at: Option<DateTime<Utc>>,
+    languages: Option<LanguageConnection>,
+}
+#[derive(Deserialize, Debug)]
+struct LanguageConnection {
+    edges: Vec<LanguageEdge>,
+}
+#[derive(Deserialize, Debug)]
+struct LanguageEdge {
+    size: u64,
+    node: LanguageName,
+}
+#[derive(Deserialize, Debug)]
+struct LanguageName {
+    name: String,
+}
+
+/// The language bytes of every repo you contributed to (the same selection as
+/// `contributed_to`), a page of 100 per call. Only run with `--contributed-languages`.
+pub fn query_contributed_languages(
+    http: &dyn Transport,
+    username: &str,
+    config: &Config,
+) -> Result<Vec<RepoLanguages>, Error> {
+    let query = r#"
+        query ContributedLanguages($username: String!, $cursor: String) {
+          user(login: $username) {
+            repositoriesContributedTo(
+              first: 100
+              after: $cursor
+              contributionTypes: [COMMIT, ISSUE, PULL_REQUEST, REPOSITORY]
+            ) {
+              nodes { nameWithOwner createdAt pushedAt languages(first: 100) { edges { size node { name } } } }
+              pageInfo { hasNextPage endCursor }
+            }
+          }
+        }
+    "#;
+    let mut repos = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let page: ContributedLanguagesPage = graphql(http, config, query, json!({ "username": username, "cursor": cursor }))?;
+        let contributed = page.repositories_contributed_to;
+        repos.extend(contributed.nodes.into_iter().flatten().map(|repo| repo_languages(repo, config)));
+        match contributed.page_info {
+            PageInfo { has_next_page: true, end_cursor: Some(next) } => cursor = Some(next),
+            _ => return Ok(repos),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct CommitLanguagesPage {
+    #[serde(rename = "contributionsCollection")]
+    contributions_collection: CommitContributions,
+}
+#[derive(Deserialize, Debug)]
+struct CommitContributions {
+    #[serde(rename = "commitContributionsByRepository")]
+    by_repository: Vec<CommitContributionsByRepository>,
+}
+#[derive(Deserialize, Debug)]
+struct CommitContributionsByRepository {
+    repository: CommittedRepository,
+}
+#[derive(Deserialize, Debug)]
+struct CommittedRepository {
+    #[serde(rename = "isPrivate", default)]
+    is_private: bool,
+    #[serde(flatten)]
+    languages: ContributedLanguageRepository,
+}
+
+/// The language bytes of the repos you committed to within `--window`, for
+/// `--lang-from contributions`. GitHub names at most 100 of them, the most committed to
+/// first. Private ones count as `--include-private-langs` says, like owned repos.
+pub fn query_commit_languages(
+    http: &dyn Transport,
+    username: &str,
+    config: &Config,
+    now: DateTime<Utc>,
+) -> Result<RepoSelection, Error> {
+    let query = r#"
+        query CommitLanguages($username: String!, $from: DateTime, $to: DateTime) {
+          user(login: $username) {
+            contributionsCollection(from: $from, to: $to) {
+              commitContributionsByRepository(maxRepositories: 100) {
+                repository { isPrivate nameWithOwner createdAt pushedAt languages(first: 100) { edges { size node { name } } } }
+              }
+            }
+          }
+        }
+    "#;
+    let (from, to) = config.window_range(now);
+    let variables = json!({ "username": username, "from": from.to_rfc3339(), "to": to.to_rfc3339() });
+    let page: CommitLanguagesPage = graphql(http, config, query, variables)?;
+    let include_private = config.include_private_langs || config.visibility == Visibility::Private;
+
+    let committed = page.contributions_collection.by_repository;
+    let listed = committed.len();
+    let repos: Vec<RepoLanguages> = committed
+        .into_iter()
+        .map(|contributions| contributions.repository)
+        .filter(|repo| include_private || !repo.is_private)
+        .map(|repo| repo_languages(repo.languages, config))
+        .collect();
+    Ok(RepoSelection { filtered: listed - repos.len(), listed, repos })
+}
+
+fn repo_languages(repo: ContributedLanguageRepository, config: &Config) -> RepoLanguages {
+    let mut bytes: HashMap<String, u64> =
+        repo.languages.into_iter().flat_map(|languages| languages.edges).map(|edge| (edge.node.name, edge.size)).collect();
+    apply_language_overrides(&mut bytes, &repo.name_with_owner, config);
+    RepoLanguages {
+        created_year: repo.created_at.map(|created| created.year()),
+        pushed_at: repo.pushed_at,
+        bytes,
+    }
+}
+
+pub fn query_user_stats(
+    http: &dyn Transport,
+    username: &str,
+    config: &Config,
+    now: DateTime<Utc>,
+) -> Result<User, Error> {
+    let (from, to) = config.window_range(now);
+
+    // Each `--skip-field` drops its selection and the variables only it uses; GitHub rejects
+    // declared variables that go unused.
+    let mut variables = vec!["$username: String!", "$from: DateTime", "$to: DateTime"];
+    let mut selections = vec![
+        extra_field_selection(&config.extra_fields)?,
+        "accountCreatedAt: createdAt".to_string(),
+    ];
+    let calendar = if config.queries(SkipField::ContributionCalendar) {
+        "contributionCalendar { weeks { contributionDays { date contributionCount } } }"
+    } else {
+        ""
+    };
+    selections.push(format!(
+        r#"contributionsCollection(from: $from, to: $to) {{
+              totalCommitContributions
+              restrictedContributionsCount
+              totalIssueContributions
+              totalPullRequestContributions
+              totalRepositoriesWithContributedCommits
+              {}
+            }}
+            pullRequests {{ totalCount }}
+            issues {{ totalCount }}
+            starredRepositories {{ totalCount }}"#,
+        calendar
+    ));
+    // A `--repo` list replaces the owned repos, fetched by name below.
+    let listed = listed_repos(config)?;
+    if config.queries(SkipField::Repositories) && listed.is_empty() {
+        variables.push("$privacy: RepositoryPrivacy");
+        selections.push(format!(
+            r#"repositories(first: 100, ownerAffiliations: OWNER, isFork: false, privacy: $privacy) {{
+              nodes {{ {} }}
+              pageInfo {{ hasNextPage endCursor }}
+            }}"#,
+            REPOSITORY_NODE_FIELDS
+        ));
+    }
+    if config.queries(SkipField::ContributedRepos) {
+        variables.push("$contributedRepos: Int!");
+        selections.push(
+            r#"repositoriesContributedTo(
+              first: $contributedRepos
+              contributionTypes: [COMMIT, ISSUE, PULL_REQUEST, REPOSITORY]
+              orderBy: { field: STARGAZERS, direction: DESC }
+            ) {
+              totalCount
+              nodes { nameWithOwner stargazerCount }
+            }"#
+            .to_string(),
+        );
+    }
+    if config.queries(SkipField::PinnedItems) {
+        selections.push(
+            r#"pinnedItems(first: 6, types: REPOSITORY) {
+              nodes { ... on Repository { name description stargazerCount primaryLanguage { name color } } }
+            }"#
+            .to_string(),
+        );
+    }
+    if config.queries(SkipField::Organizations) {
+        variables.push("$organizations: Int!");
+        selections.push("organizations(first: $organizations) { nodes { login avatarUrl } }".to_string());
+    }
+    let query = format!(
+        "query({}) {{\n  user(login: $username) {{\n    {}\n  }}\n}}",
+        variables.join(", "),
+        selections.join("\n    ")
+    );
+
+    let mut user: User = graphql(
+        http,
+        config,
+        &query,
+        json!({
+            "username": username,
+            "from": from.to_rfc3339(),
+            "to": to.to_rfc3339(),
+            "privacy": config.visibility.graphql_privacy(),
+            "contributedRepos": config.contributed_repos,
+            "organizations": config.organizations,
+        }),
+    )?;
+    fetch_remaining_repositories(http, username, config, &mut user.repositories)?;
+    if config.queries(SkipField::Repositories) && !listed.is_empty() {
+        user.repositories.nodes = query_listed_repositories(http, config, &listed)?;
+    }
+    Ok(user)
+}
+
+/// Repos one `query_listed_repositories` request looks up, each as its own aliased field.
+const REPOS_PER_QUERY: usize = 50;
+
+/// The listed `owner/repo`s as repository nodes, in order, for the star sum and everything
+/// else read from owned repos. Repos GitHub can't resolve come back null, with a warning.
+fn query_listed_repositories(http: &dyn Transport, config: &Config, names: &[String]) -> Result<Vec<Option<Stargazer>>, Error> {
+    let mut nodes = Vec::new();
+    for chunk in names.chunks(REPOS_PER_QUERY) {
+        let fields: String = chunk
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let (owner, repo) = name.split_once('/').unwrap_or_default();
+                format!("r{}: repository(owner: {}, name: {}) {{ {} }}\n", i, json!(owner), json!(repo), REPOSITORY_NODE_FIELDS)
+            })
+            .collect();
+        let query = format!("query ListedRepositories {{ {} }}", fields);
+        let mut found: HashMap<String, Option<Stargazer>> = graphql_at(http, config, &query, json!({}), None)?;
+        nodes.extend((0..chunk.len()).map(|i| found.remove(&format!("r{}", i)).flatten()));
+    }
+    Ok(nodes)
+}
+
+/// The counts REST can stand in for when GraphQL is down. Commit contributions and the
+/// contributed-to repos only exist in GraphQL, so a fallback run goes without them.
+#[derive(Debug, Clone)]
+pub struct RestUserStats {
+    pub total_stars: u64,
+    pub total_prs: u64,
+    pub total_issues: u64,
+    pub prs_this_year: u64,
+    pub issues_this_year: u64,
+    pub stars_given: u64,
+    /// In kilobytes, like GraphQL's `diskUsage`.
+    pub total_disk_usage: u64,
+}
+
+pub fn query_rest_stats(
+    http: &dyn Transport,
+    username: &str,
+    config: &Config,
+    now: DateTime<Utc>,
+) -> Result<RestUserStats, Error> {
+    // Same selection as the GraphQL star sum: owned, non-fork repos under `--visibility`, or
+    // every `--repo` as listed.
+    let listed = !listed_repos(config)?.is_empty();
+    let repos = fetch_repos(http, config)?;
+    let owned: Vec<&serde_json::Value> = repos
+        .iter()
+        .filter(|repo| listed || !repo["fork"].as_bool().unwrap_or(false))
+        .filter(|repo| listed || repo["owner"]["login"].as_str().is_some_and(|owner| owner.eq_ignore_ascii_case(username)))
+        .collect();
+    let total_stars = owned.iter().filter_map(|repo| repo["stargazers_count"].as_u64()).sum();
+    let total_disk_usage = owned.iter().filter_map(|repo| repo["size"].as_u64()).sum();
+    let (from, to) = config.window_range(now);
+    let this_year = format!("created:{}..{}", from.format("%Y-%m-%d"), to.format("%Y-%m-%d"));
+
+    Ok(RestUserStats {
+        total_stars,
+        total_prs: search_count(http, &format!("author:{} type:pr", username))?,
+        total_issues: search_count(http, &format!("author:{} type:issue", username))?,
+        prs_this_year: search_count(http, &format!("author:{} type:pr {}", username, this_year))?,
+        issues_this_year: search_count(http, &format!("author:{} type:issue {}", username, this_year))?,
+        stars_given: count_starred(http, username)?,
+        total_disk_usage,
+    })
+}
+
+/// The `total_count` of an issue search, without fetching more than one result: the count
+/// covers every match, so there are no pages to walk.
+fn search_count(http: &dyn Transport, query: &str) -> Result<u64, Error> {
+    let url = format!("https://api.github.com/search/issues?q={}&per_page=1", query.replace(' ', "+"));
+    let mut response = http.get(&url)?;
+    if let Some(reset) = search_rate_limit_reset(&response) {
+        let wait = (reset - Utc::now()).to_std().unwrap_or_default() + std::time::Duration::from_secs(1);
+        if wait > MAX_SEARCH_RATE_LIMIT_WAIT {
+            return Err(Error::RateLimited { reset });
+        }
+        std::thread::sleep(wait);
+        response = http.get(&url)?;
+    }
+    let result: serde_json::Value = response.rest_json()?;
+    if result["incomplete_results"].as_bool() == Some(true) {
+        eprintln!("warning: GitHub search timed out on {:?}, its count may be low", query);
+    }
+    result["total_count"]
+        .as_u64()
+        .ok_or_else(|| Error::Http("GitHub search returned no total_count".to_string()))
+}
+
+/// The search API has its own rate limit of 30 requests a minute, so waiting out its reset is
+/// worth it; anything longer is reported instead.
+const MAX_SEARCH_RATE_LIMIT_WAIT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// When the rate limit resets, if `response` was turned away by it.
+fn search_rate_limit_reset(response: &crate::http::Response) -> Option<DateTime<Utc>> {
+    if !matches!(response.status, 403 | 429) || response.header("x-ratelimit-remaining") != Some("0") {
+        return None;
+    }
+    let reset: i64 = response.header("x-ratelimit-reset")?.parse().ok()?;
+    Utc.timestamp_opt(reset, 0).single()
+}
+
+/// Pull requests and issues opened in repos owned by someone else.
+#[derive(Debug, Clone, Copy)]
+pub struct ExternalContributions {
+    pub prs: u64,
+    pub issues: u64,
+}
+
+pub fn query_external_contributions(
+    http: &dyn Transport,
+    username: &str,
+) -> Result<ExternalContributions, Error> {
+    Ok(ExternalContributions {
+        prs: search_count(http, &format!("author:{0} type:pr -user:{0}", username))?,
+        issues: search_count(http, &format!("author:{0} type:issue -user:{0}", username))?,
+    })
+}
+
+/// With one repo per page, the last page number of the starred listing is the count.
+fn count_starred(http: &dyn Transport, username: &str) -> Result<u64, Error> {
+    let response = http.get(&format!("https://api.github.com/users/{}/starred?per_page=1", username))?;
+    if let Some(error) = response.github_error() {
+        return Err(Error::Http(format!("GitHub API error: {}", error)));
+    }
+    if let Some(last_page) = response.header("link").and_then(last_page_from_link) {
+        return Ok(last_page.into());
+    }
+    let starred: Vec<serde_json::Value> = response.rest_json()?;
+    Ok(starred.len() as u64)
+}
+
+#[derive(Deserialize, Debug)]
+struct CreatedAt {
+    #[serde(rename = "createdAt")]
+    created_at: DateTime<Utc>,
+}
+
+pub fn query_created_at(
+    http: &dyn Transport,
+    username: &str,
+    config: &Config,
+) -> Result<DateTime<Utc>, Error> {
+    let query = r#"
+        query($username: String!) {
+          user(login: $username) { createdAt }
+        }
+    "#;
+    let user: CreatedAt = graphql(http, config, query, json!({ "username": username }))?;
+    Ok(user.created_at)
+}
+
+/// Calendar years whose commits one `query_yearly_commits` request asks for. Each aliased
+/// `contributionsCollection` adds to the query's cost, so long-lived accounts are split.
+const YEARS_PER_QUERY: usize = 8;
+
+/// The contributions of each calendar year in `years`. GitHub caps a
+/// `contributionsCollection` window at one year, so each year is its own aliased field
+/// (`y2019: contributionsCollection(...)`), up to `YEARS_PER_QUERY` of them per round trip.
+pub fn query_yearly_commits(
+    http: &dyn Transport,
+    username: &str,
+    years: std::ops::RangeInclusive<i32>,
+    config: &Config,
+) -> Result<Vec<(i32, ContributionsCollection)>, Error> {
+    let years: Vec<i32> = years.collect();
+    let mut commits = Vec::new();
+    for chunk in years.chunks(YEARS_PER_QUERY) {
+        let fields: String = chunk
+            .iter()
+            .map(|year| {
+                let from = Utc.with_ymd_and_hms(*year, 1, 1, 0, 0, 0).unwrap();
+                // The `--as-of` year stops at that day.
+                let to = Utc.with_ymd_and_hms(*year, 12, 31, 23, 59, 59).unwrap();
+                let to = to.min(config.counts_until(to));
+                format!(
+                    r#"y{}: contributionsCollection(from: "{}", to: "{}") {{
+                      totalCommitContributions
+                      restrictedContributionsCount
+                      totalIssueContributions
+                      totalPullRequestContributions
+                    }}
+                    "#,
+                    year,
+                    from.to_rfc3339(),
+                    to.to_rfc3339()
+                )
+            })
+            .collect();
+        let query = format!("query YearlyCommits($username: String!) {{ user(login: $username) {{ {} }} }}", fields);
+        let mut user: HashMap<String, ContributionsCollection> = graphql(http, config, &query, json!({ "username": username }))?;
+        for year in chunk {
+            let collection = user
+                .remove(&format!("y{}", year))
+                .ok_or_else(|| Error::Http(format!("GraphQL response is missing the {} contributions", year)))?;
+            commits.push((*year, collection));
+        }
+    }
+    Ok(commits)
+}
+
+#[derive(Deserialize, Debug)]
+struct CommitsBetweenPage {
+    #[serde(rename = "contributionsCollection")]
+    contributions_collection: ContributionsCollection,
+}
+
+/// Commit contributions (plus restricted ones, if `include_restricted`) from `from` to `to`,
+/// at most a year apart as GitHub allows, for `--since-last-run`.
+pub fn query_commits_between(
+    http: &dyn Transport,
+    username: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    include_restricted: bool,
+    config: &Config,
+) -> Result<u64, Error> {
+    let query = r#"
+        query CommitsBetween($username: String!, $from: DateTime, $to: DateTime) {
+          user(login: $username) {
+            contributionsCollection(from: $from, to: $to) {
+              totalCommitContributions
+              restrictedContributionsCount
+              totalIssueContributions
+              totalPullRequestContributions
+            }
+          }
+        }
+    "#;
+    let variables = json!({ "username": username, "from": from.to_rfc3339(), "to": to.to_rfc3339() });
+    let page: CommitsBetweenPage = graphql(http, config, query, variables)?;
+    Ok(page.contributions_collection.commits(include_restricted))
+}
+
+#[derive(Deserialize, Debug)]
+struct NodeId {
+    id: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct RestUserId {
+    id: u64,
+}
+
+/// The `CommitAuthor` filter matching one `--exclude-authors` entry: emails match as given,
+/// user logins are looked up for their node ID. GraphQL doesn't resolve bots, so a
+/// `name[bot]` login becomes its noreply email, `<id>+name[bot]@users.noreply.github.com`,
+/// with the ID from REST. `None` if the login is unknown.
+fn commit_author(
+    http: &dyn Transport,
+    config: &Config,
+    author: &str,
+) -> Result<Option<serde_json::Value>, Error> {
+    if author.contains('@') {
+        return Ok(Some(json!({ "emails": [author] })));
+    }
+    if author.ends_with("[bot]") {
+        let response = http.get(&format!("https://api.github.com/users/{}", author))?;
+        if response.status == 404 {
+            eprintln!("warning: not excluding {}: GitHub has no such bot", author);
+            return Ok(None);
+        }
+        let bot: RestUserId = response.rest_json()?;
+        return Ok(Some(json!({ "emails": [format!("{}+{}@users.noreply.github.com", bot.id, author)] })));
+    }
+    let query = r#"
+        query AuthorId($login: String!) {
+          user: repositoryOwner(login: $login) { id }
+        }
+    "#;
+    match graphql::<NodeId>(http, config, query, json!({ "login": author })) {
+        Ok(owner) => Ok(Some(json!({ "id": owner.id }))),
+        Err(e @ Error::Http(_)) => Err(e),
+        Err(e) => {
+            eprintln!("warning: not excluding {}: {}", author, e);
+            Ok(None)
+        }
+    }
+}
+
+/// Commits in the window on the default branch of each owned, non-fork repo, less those by
+/// `--exclude-authors`, 100 repos per query. Each excluded author adds an aliased `history`
+/// count per repo. Authors that resolve to the same filter are subtracted once, but a person
+/// given by both login and email is subtracted twice, as GitHub can't tell they're the same.
+pub fn query_default_branch_commits(
+    http: &dyn Transport,
+    username: &str,
+    config: &Config,
+    now: DateTime<Utc>,
+) -> Result<u64, Error> {
+    let (since, until) = config.window_range(now);
+    let mut variables = json!({
+        "username": username,
+        "since": since.to_rfc3339(),
+        "until": until.to_rfc3339(),
+        "privacy": config.visibility.graphql_privacy(),
+    });
+    let mut declarations = String::new();
+    let mut excluded_counts = String::new();
+    let mut filters = Vec::new();
+    for author in &config.exclude_authors {
+        let Some(filter) = commit_author(http, config, author)? else {
+            continue;
+        };
+        if filters.contains(&filter) {
+            continue;
+        }
+        let excluded = filters.len();
+        declarations.push_str(&format!(", $excluded{}: CommitAuthor", excluded));
+        excluded_counts.push_str(&format!(
+            "excluded{0}: history(since: $since, until: $until, author: $excluded{0}) {{ totalCount }}\n",
+            excluded
+        ));
+        variables[format!("excluded{}", excluded)] = filter.clone();
+        filters.push(filter);
+    }
+
+    let query = format!(
+        r#"
+        query DefaultBranchCommits($username: String!, $since: GitTimestamp, $until: GitTimestamp, $privacy: RepositoryPrivacy, $cursor: String{}) {{
+          user(login: $username) {{
+            repositories(first: 100, after: $cursor, ownerAffiliations: OWNER, isFork: false, privacy: $privacy) {{
+              nodes {{
+                defaultBranchRef {{
+                  target {{
+                    ... on Commit {{
+                      history(since: $since, until: $until) {{ totalCount }}
+                      {}
+                    }}
+                  }}
+                }}
+              }}
+              pageInfo {{ hasNextPage endCursor }}
+            }}
+          }}
+        }}
+    "#,
+        declarations, excluded_counts
+    );
+
+    let count = |commit: &serde_json::Value, field: &str| commit[field]["totalCount"].as_u64().unwrap_or(0);
+    let mut total = 0;
+    loop {
+        let user: serde_json::Value = graphql(http, config, &query, variables.clone())?;
+        let repositories = &user["repositories"];
+        total += repositories["nodes"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            // Empty repos have no default branch and count as zero.
+            .map(|repo| &repo["defaultBranchRef"]["target"])
+            .map(|commit| {
+                let excluded: u64 = (0..filters.len()).map(|i| count(commit, &format!("excluded{}", i))).sum();
+                count(commit, "history").saturating_sub(excluded)
+            })
+            .sum::<u64>();
+        match repositories["pageInfo"]["endCursor"].as_str() {
+            Some(cursor) if repositories["pageInfo"]["hasNextPage"].as_bool() == Some(true) => {
+                variables["cursor"] = json!(cursor);
+            }
+            _ => return Ok(total),
+        }
+    }
+}
+
+/// Runs a query and returns its `user` (or whatever the query aliased to `user`). Transport
+/// failures and HTTP errors come back as `Error::Http`, a rejected token as `Error::Auth`,
+/// and a null `user` as `Error::UserNotFound`.
+fn graphql<T: DeserializeOwned>(
+    http: &dyn Transport,
+    config: &Config,
+    query: &str,
+    variables: serde_json::Value,
+) -> Result<T, Error> {
+    graphql_at(http, config, query, variables, Some("user"))
+}
+
+/// `graphql`, reading the field `root` of `data`, or all of `data` for `None`.
+fn graphql_at<T: DeserializeOwned>(
+    http: &dyn Transport,
+    config: &Config,
+    query: &str,
+    variables: serde_json::Value,
+    root: Option<&str>,
+) -> Result<T, Error> {
+    let response = http.post_json(GRAPHQL_URL, &json!({ "query": query, "variables": variables }))?;
+
+    if response.status == 401 {
+        return Err(Error::Auth(format!("GitHub rejected the token: {}", response.body)));
+    }
+    if !response.is_success() {
+        return Err(Error::Http(format!("GitHub API returned non-success status: {}", response.body)));
+    }
+
+    let gql_response: GraphQLResponse = response.json()?;
+    let errors = gql_response.errors.unwrap_or_default();
+    let user = gql_response
+        .data
+        .and_then(|mut data| match root {
+            Some(root) => data.get_mut(root).map(serde_json::Value::take),
+            None => Some(data),
+        })
+        .filter(|user| !user.is_null());
+
+    if errors.iter().any(|error| error.kind.as_deref() == Some("RATE_LIMITED")) {
+        let reset = response
+            .header("x-ratelimit-reset")
+            .and_then(|reset| reset.parse().ok())
+            .and_then(|reset| Utc.timestamp_opt(reset, 0).single())
+            .unwrap_or_else(Utc::now);
+        return Err(Error::RateLimited { reset });
+    }
+    if user.is_none() && errors.iter().any(|error| error.kind.as_deref() == Some("NOT_FOUND")) {
+        let login = variables["username"].as_str().or(variables["login"].as_str()).unwrap_or_default();
+        return Err(Error::UserNotFound(login.to_string()));
+    }
+    let fatal = user.is_none() || config.strict || errors.iter().any(|error| error.is_fatal(root.iter().count()));
+    if fatal && !errors.is_empty() {
+        return Err(Error::GraphQl(errors.iter().map(GraphQLError::describe).collect()));
+    }
+    for error in &errors {
+        eprintln!("warning: partial GraphQL data: {}", error.describe());
+    }
+
+    // A null root without errors means the owner the query looked up doesn't exist; GitHub
+    // answered fine, so this isn't an `Error::Http` to retry over REST.
+    let user = user.ok_or_else(|| match root {
+        Some(_) => {
+            let login = variables["username"].as_str().or(variables["login"].as_str()).unwrap_or_default();
+            Error::UserNotFound(login.to_string())
+        }
+        None => Error::GraphQl(vec!["GitHub returned no data".to_string()]),
+    })?;
+    Ok(serde_json::from_value(user)?)
+}
@@ -0,0 +1,211 @@
+use crate::config::{Config, StatCategory, Window};
+use crate::error::Error;
+use crate::stats::Stats;
+use chrono::prelude::*;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// How many of the top languages each history line keeps.
+const HISTORY_LANGUAGES: usize = 3;
+
+/// How many points the trend series hold, this run's included.
+const TREND_POINTS: usize = 7;
+
+/// How far, in percentage points, a language's share can move between runs and stay `flat`.
+const FLAT_POINTS: f64 = 0.5;
+
+/// One line of the `--history-file`: a run's headline totals, for charting growth over time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub timestamp: DateTime<Utc>,
+    pub total_stars: u64,
+    pub total_commits_this_year: Option<u64>,
+    pub total_prs: u64,
+    pub total_issues: u64,
+    pub contributed_to: Option<u64>,
+    pub stars_given: u64,
+    pub top_languages: Vec<(String, f64)>,
+}
+
+impl HistoryEntry {
+    pub fn from_stats(stats: &Stats) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: stats.generated_at,
+            total_stars: stats.total_stars,
+            total_commits_this_year: stats.total_commits_this_year,
+            total_prs: stats.total_prs,
+            total_issues: stats.total_issues,
+            contributed_to: stats.contributed_to,
+            stars_given: stats.stars_given,
+            top_languages: stats.languages.iter().take(HISTORY_LANGUAGES).cloned().collect(),
+        }
+    }
+}
+
+/// Appends `stats` to the history as one JSON line. The file is only ever appended to, under
+/// an exclusive lock so that overlapping runs (say, a cron job and a manual one) can't
+/// interleave their lines.
+pub fn append_history(path: &Path, stats: &Stats) -> Result<(), Box<dyn std::error::Error>> {
+    let line = serde_json::to_string(&HistoryEntry::from_stats(stats))? + "\n";
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.lock()?;
+    file.write_all(line.as_bytes())?;
+    file.flush()?;
+    Ok(())
+}
+
+/// Reads every entry of the history, oldest first. A missing file is an empty history; lines
+/// that don't parse (a run killed mid-write, an older format) are skipped with a warning.
+pub fn read_history(path: &Path) -> Vec<HistoryEntry> {
+    let Ok(text) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(i, line)| match serde_json::from_str(line) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                eprintln!("warning: skipping line {} of {}: {}", i + 1, path.display(), e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// How this run compares with the ones recorded before it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trend {
+    /// When the last recorded run happened, the baseline the deltas count from.
+    pub since: DateTime<Utc>,
+    pub stars_delta: i64,
+    /// `None` when either run lacks a commit count.
+    pub commits_delta: Option<i64>,
+    /// The last few star counts, oldest first and ending with this run's, for a sparkline.
+    pub stars_history: Vec<u64>,
+    /// Likewise for the commit counts, skipping runs that had none.
+    pub commits_history: Vec<u64>,
+    /// Which way each charted language moved since the last recorded run, for the ones the
+    /// history can tell about; see `language_trends`.
+    #[serde(default)]
+    pub languages: BTreeMap<String, LanguageTrend>,
+}
+
+/// Which way a language's share moved since the last recorded run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LanguageTrend {
+    Up,
+    Down,
+    /// Within half a percentage point.
+    Flat,
+    /// Not in any recorded run.
+    New,
+}
+
+/// Compares each of `current`'s languages with its share in the last recorded run. History
+/// lines only keep the top three, so a language missing from the last one is only placed if
+/// it's in the top three now: `new` if no run recorded it, `up` if it climbed back in. Below
+/// that the history can't tell, and the language is left out.
+fn language_trends(history: &[HistoryEntry], current: &Stats) -> BTreeMap<String, LanguageTrend> {
+    let Some(last) = history.last() else {
+        return BTreeMap::new();
+    };
+    let recorded = |lang: &str| history.iter().any(|entry| entry.top_languages.iter().any(|(name, _)| name == lang));
+    current
+        .languages
+        .iter()
+        .enumerate()
+        .filter_map(|(rank, (lang, percentage))| {
+            let trend = match last.top_languages.iter().find(|(name, _)| name == lang) {
+                Some((_, before)) if percentage - before > FLAT_POINTS => LanguageTrend::Up,
+                Some((_, before)) if before - percentage > FLAT_POINTS => LanguageTrend::Down,
+                Some(_) => LanguageTrend::Flat,
+                None if rank >= HISTORY_LANGUAGES => return None,
+                None if recorded(lang) => LanguageTrend::Up,
+                None => LanguageTrend::New,
+            };
+            Some((lang.clone(), trend))
+        })
+        .collect()
+}
+
+/// Compares `current` with the end of `history`. `None` on the first run, with nothing
+/// recorded yet.
+pub fn trend(history: &[HistoryEntry], current: &Stats) -> Option<Trend> {
+    let last = history.last()?;
+    let recent = |values: Vec<u64>| values[values.len().saturating_sub(TREND_POINTS)..].to_vec();
+    let delta = |ours: u64, theirs: u64| ours as i64 - theirs as i64;
+    Some(Trend {
+        since: last.timestamp,
+        stars_delta: delta(current.total_stars, last.total_stars),
+        commits_delta: current
+            .total_commits_this_year
+            .zip(last.total_commits_this_year)
+            .map(|(ours, theirs)| delta(ours, theirs)),
+        stars_history: recent(history.iter().map(|entry| entry.total_stars).chain([current.total_stars]).collect()),
+        commits_history: recent(
+            history
+                .iter()
+                .map(|entry| entry.total_commits_this_year)
+                .chain([current.total_commits_this_year])
+                .flatten()
+                .collect(),
+        ),
+        languages: language_trends(history, current),
+    })
+}
+
+/// The count `--regression-guard` watches for `category`, as a history line has it. `None`
+/// when the run had none.
+fn guarded_count(entry: &HistoryEntry, category: StatCategory) -> Option<u64> {
+    match category {
+        StatCategory::Stars => Some(entry.total_stars),
+        StatCategory::Commits => entry.total_commits_this_year,
+        StatCategory::Prs => Some(entry.total_prs),
+        StatCategory::Issues => Some(entry.total_issues),
+        StatCategory::Contributions => entry.contributed_to,
+        StatCategory::Starred => Some(entry.stars_given),
+        StatCategory::Languages => None,
+    }
+}
+
+/// Fails when a `--regression-guard` stat of `current` fell more than `--regression-threshold`
+/// percent below the end of `history`, naming every one that did. Stats either run lacks are
+/// skipped, as are the commits when a calendar year started in between.
+pub fn check_regression(history: &[HistoryEntry], current: &Stats, config: &Config) -> Result<(), Error> {
+    let Some(last) = history.last() else {
+        return Ok(());
+    };
+    if config.regression_guard.contains(&StatCategory::Languages) {
+        return Err(Error::Config("--regression-guard languages: the languages have no count to guard".to_string()));
+    }
+    let now = HistoryEntry::from_stats(current);
+    let offset = config.utc_offset;
+    let new_year = now.timestamp.with_timezone(&offset).year() != last.timestamp.with_timezone(&offset).year();
+    let regressions: Vec<String> = config
+        .regression_guard
+        .iter()
+        .filter(|&&category| !(category == StatCategory::Commits && config.window == Window::CalendarYear && new_year))
+        .filter_map(|&category| {
+            let (before, after) = (guarded_count(last, category)?, guarded_count(&now, category)?);
+            let drop = before.saturating_sub(after) as f64 / before as f64 * 100.0;
+            (before > 0 && drop > config.regression_threshold).then(|| {
+                format!("{} fell from {} to {} ({:.0}% down)", category.to_possible_value().expect("no skipped variants").get_name(), before, after, drop)
+            })
+        })
+        .collect();
+    if regressions.is_empty() {
+        return Ok(());
+    }
+    Err(Error::Regression(format!(
+        "{} since the last run at {}, over --regression-threshold {}%",
+        regressions.join(", "),
+        last.timestamp.to_rfc3339(),
+        config.regression_threshold
+    )))
+}
@@ -0,0 +1,181 @@
+use reqwest::blocking::Client;
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderName, HeaderValue};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::Duration;
+
+pub(crate) const USER_AGENT: &str = "Rust GitHub README Generator";
+
+/// A fully-read HTTP response. Header names are stored lowercased.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Response {
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl Response {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_ascii_lowercase()).map(String::as_str)
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_str(&self.body)
+    }
+
+    /// What went wrong, if this is an error status or GitHub's error object
+    /// (`{"message": "Bad credentials", "documentation_url": ...}`), which can come back where
+    /// an array was expected.
+    pub fn github_error(&self) -> Option<String> {
+        let body: Option<serde_json::Value> = serde_json::from_str(&self.body).ok();
+        let message = body.as_ref().and_then(|body| body["message"].as_str());
+        let error_object = message.is_some() && body.as_ref().is_some_and(|body| body.get("documentation_url").is_some());
+        if self.is_success() && !error_object {
+            return None;
+        }
+        Some(match message {
+            Some(message) if self.is_success() => message.to_string(),
+            Some(message) => format!("HTTP {}: {}", self.status, message),
+            None => format!("HTTP {}: {}", self.status, self.body.trim()),
+        })
+    }
+
+    /// `Error::Auth` if GitHub turned the token away (a 401: invalid, revoked or expired),
+    /// which no retry or fallback will get past.
+    pub fn rejected_token(&self) -> Option<crate::error::Error> {
+        let error = self.github_error().filter(|_| self.status == 401)?;
+        Some(crate::error::Error::Auth(format!("GitHub rejected the token: {}", error)))
+    }
+
+    /// Parses a REST body as `T`, reporting `github_error` rather than a confusing type
+    /// mismatch when GitHub answered with an error.
+    pub fn rest_json<T: DeserializeOwned>(&self) -> Result<T, crate::error::Error> {
+        if let Some(error) = self.rejected_token() {
+            return Err(error);
+        }
+        if let Some(error) = self.github_error() {
+            return Err(crate::error::Error::Http(format!("GitHub API error: {}", error)));
+        }
+        serde_json::from_str(&self.body)
+            .map_err(|e| crate::error::Error::Http(format!("unexpected GitHub API response ({}): {}", e, self.body.trim())))
+    }
+}
+
+/// Everything the stat collectors need from the network. Swapping the implementation lets
+/// tests replay recorded GitHub responses instead of hitting the API.
+pub trait Transport: Sync {
+    fn get(&self, url: &str) -> Result<Response, Box<dyn Error>>;
+    fn post_json(&self, url: &str, body: &serde_json::Value) -> Result<Response, Box<dyn Error>>;
+}
+
+/// Where the transport gets the token for each request. A fixed token is its own source;
+/// short-lived tokens (see `auth::AppTokenSource`) can renew themselves behind this.
+pub trait TokenSource: Send + Sync {
+    fn token(&self) -> Result<String, String>;
+}
+
+impl TokenSource for String {
+    fn token(&self) -> Result<String, String> {
+        Ok(self.clone())
+    }
+}
+
+/// The real transport, authenticating every request with a token from its `TokenSource`.
+pub struct HttpTransport {
+    client: Client,
+    token: Box<dyn TokenSource>,
+}
+
+impl HttpTransport {
+    pub fn new(token: impl Into<String>) -> Self {
+        HttpTransport::with_token_source(token.into())
+    }
+
+    pub fn with_token_source(token: impl TokenSource + 'static) -> Self {
+        HttpTransport {
+            client: Client::new(),
+            token: Box::new(token),
+        }
+    }
+
+    /// Sends `headers` (see `parse_headers`) with every request too, and gives up on each
+    /// request after `timeout`.
+    pub fn with_client_options(mut self, headers: HeaderMap, timeout: Duration) -> Result<Self, String> {
+        self.client = build_client(headers, timeout)?;
+        Ok(self)
+    }
+
+    /// Sends requests through `client` (see `build_client`) rather than a default one.
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+}
+
+/// A client that sends `headers` with every request and gives up on each after `timeout`.
+pub fn build_client(headers: HeaderMap, timeout: Duration) -> Result<Client, String> {
+    Client::builder()
+        .default_headers(headers)
+        .timeout(timeout)
+        .build()
+        .map_err(|e| format!("failed to build the HTTP client: {}", e))
+}
+
+/// Parses `--header` values, `Name: Value` each. An `Authorization` header is dropped with
+/// a warning, since it would replace the token.
+pub fn parse_headers(headers: &[String]) -> Result<HeaderMap, String> {
+    let mut map = HeaderMap::new();
+    for header in headers {
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| format!("invalid --header {:?}: expected Name: Value", header))?;
+        let name = HeaderName::try_from(name.trim()).map_err(|_| format!("invalid --header name {:?}", name.trim()))?;
+        let value = HeaderValue::try_from(value.trim()).map_err(|_| format!("invalid --header value for {}", name))?;
+        if name == AUTHORIZATION {
+            eprintln!("warning: ignoring --header {}: the token sets Authorization", name);
+            continue;
+        }
+        map.append(name, value);
+    }
+    Ok(map)
+}
+
+impl Transport for HttpTransport {
+    fn get(&self, url: &str) -> Result<Response, Box<dyn Error>> {
+        let response = self
+            .client
+            .get(url)
+            .header("Authorization", format!("Bearer {}", self.token.token()?))
+            .header("User-Agent", USER_AGENT)
+            .send()?;
+        read_response(response)
+    }
+
+    fn post_json(&self, url: &str, body: &serde_json::Value) -> Result<Response, Box<dyn Error>> {
+        let response = self
+            .client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", self.token.token()?))
+            .header("User-Agent", USER_AGENT)
+            .json(body)
+            .send()?;
+        read_response(response)
+    }
+}
+
+fn read_response(response: reqwest::blocking::Response) -> Result<Response, Box<dyn Error>> {
+    let status = response.status().as_u16();
+    let headers = response
+        .headers()
+        .iter()
+        .filter_map(|(name, value)| Some((name.as_str().to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    let body = response.text()?;
+    Ok(Response { status, headers, body })
+}
@@ -0,0 +1,665 @@
+use crate::config::{AuthMode, Config, LangMetric, Visibility};
+use crate::error::Error;
+use crate::github::INSTALLATION_REPOS_URL;
+use crate::http::{Response, Transport};
+use chrono::prelude::*;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+
+/// Spellings other data sources use for languages that GitHub (Linguist) names differently,
+/// keyed by lowercase.
+const LANGUAGE_ALIASES: &[(&str, &str)] = &[
+    ("jupyter", "Jupyter Notebook"),
+    ("ipynb", "Jupyter Notebook"),
+    ("vb.net", "Visual Basic .NET"),
+    ("vb", "Visual Basic .NET"),
+    ("cpp", "C++"),
+    ("c plus plus", "C++"),
+    ("csharp", "C#"),
+    ("fsharp", "F#"),
+    ("golang", "Go"),
+    ("js", "JavaScript"),
+    ("ts", "TypeScript"),
+    ("bash", "Shell"),
+    ("sh", "Shell"),
+    ("objc", "Objective-C"),
+    ("objective c", "Objective-C"),
+    ("viml", "Vim Script"),
+];
+
+/// The Linguist name for a known alias, matched case-insensitively.
+pub fn canonical_language(name: &str) -> Option<&'static str> {
+    let lower = name.to_lowercase();
+    LANGUAGE_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == lower)
+        .map(|(_, canonical)| *canonical)
+}
+
+/// Sums per-repo byte maps into one, merging keys that differ only in case or are known
+/// aliases so a language never shows up as two slices. Unknown languages keep whichever
+/// spelling carried the most bytes.
+pub fn merge_language_maps(maps: impl IntoIterator<Item = HashMap<String, u64>>) -> BTreeMap<String, u64> {
+    // BTreeMaps keep the aggregation order independent of HashMap iteration, so equal
+    // percentages always come out in the same order.
+    let mut groups: BTreeMap<String, BTreeMap<String, u64>> = BTreeMap::new();
+    for map in maps {
+        for (lang, bytes) in map {
+            let key = match canonical_language(&lang) {
+                Some(canonical) => canonical.to_lowercase(),
+                None => lang.to_lowercase(),
+            };
+            *groups.entry(key).or_default().entry(lang).or_insert(0) += bytes;
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(key, spellings)| {
+            let total = spellings.values().sum();
+            let name = LANGUAGE_ALIASES
+                .iter()
+                .map(|(_, canonical)| *canonical)
+                .find(|canonical| canonical.to_lowercase() == key)
+                .map(String::from)
+                .unwrap_or_else(|| {
+                    let most_bytes = spellings.iter().max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)));
+                    most_bytes.map(|(spelling, _)| spelling.clone()).unwrap_or(key)
+                });
+            (name, total)
+        })
+        .collect()
+}
+
+fn repos_page_url(config: &Config, page: u32) -> String {
+    // An installation token can't list `/user/repos`; it lists the repos the app was
+    // installed on instead, which `--repos-type` and `--visibility` don't narrow server-side.
+    if config.auth == AuthMode::App {
+        return format!("{}?per_page=100&page={}", INSTALLATION_REPOS_URL, page);
+    }
+    // GitHub rejects `type` combined with `visibility`, so a filtered listing expresses the
+    // same selection through `affiliation` instead.
+    let selection = match config.visibility.rest_visibility() {
+        None => format!("type={}", config.repos_type.rest_type()),
+        Some(visibility) => format!(
+            "affiliation={}&visibility={}",
+            config.repos_type.rest_affiliation(),
+            visibility
+        ),
+    };
+    // With a cap, the most recently pushed repos are the ones worth keeping.
+    let sort = if config.max_repos.is_some() { "&sort=pushed" } else { "" };
+    format!(
+        "https://api.github.com/user/repos?{}{}&per_page=100&page={}",
+        selection, sort, page
+    )
+}
+
+/// The repos on one page of the listing `repos_page_url` points at. The installation
+/// listing wraps them in a `repositories` field.
+fn repos_page(config: &Config, response: &Response) -> Result<Vec<serde_json::Value>, Error> {
+    #[derive(Deserialize)]
+    struct InstallationRepos {
+        repositories: Vec<serde_json::Value>,
+    }
+    match config.auth {
+        AuthMode::Token => response.rest_json(),
+        AuthMode::App => response.rest_json::<InstallationRepos>().map(|page| page.repositories),
+    }
+}
+
+/// Pulls the page number out of the `rel="last"` entry of a `Link` header.
+pub(crate) fn last_page_from_link(link: &str) -> Option<u32> {
+    link.split(',')
+        .find(|part| part.contains(r#"rel="last""#))
+        .and_then(|part| part.split_once('<')?.1.split_once('>').map(|(url, _)| url))
+        .and_then(|url| url.split_once('?').map(|(_, query)| query))
+        .and_then(|query| query.split('&').find_map(|param| param.strip_prefix("page=")))
+        .and_then(|page| page.parse().ok())
+}
+
+/// The `--repo`s followed by the `--repos-file` names, without repeats. When there are any,
+/// they stand in for the listing of your repos.
+pub fn listed_repos(config: &Config) -> Result<Vec<String>, Error> {
+    let mut names = config.repos.clone();
+    if let Some(path) = &config.repos_file {
+        let text = fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("failed to read --repos-file {}: {}", path.display(), e)))?;
+        names.extend(
+            text.lines()
+                .map(|line| line.split('#').next().unwrap_or_default().trim())
+                .filter(|line| !line.is_empty())
+                .map(String::from),
+        );
+    }
+    if let Some(name) = names.iter().find(|name| !is_repo_name(name)) {
+        return Err(Error::Config(format!("expected a repo as owner/repo, got {:?}", name)));
+    }
+    let mut seen = HashSet::new();
+    names.retain(|name| seen.insert(name.to_lowercase()));
+    Ok(names)
+}
+
+/// Whether `name` is spelled like an `owner/repo` GitHub could have: a login of letters,
+/// digits and hyphens, and a repo name of those plus `_` and `.`.
+fn is_repo_name(name: &str) -> bool {
+    let Some((owner, repo)) = name.split_once('/') else {
+        return false;
+    };
+    let owner_ok = !owner.is_empty() && owner.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    let repo_ok = !matches!(repo, "" | "." | "..")
+        && repo.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    owner_ok && repo_ok
+}
+
+/// Each listed repo as `GET /repos/{owner}/{repo}` has it, the same shape the listing has.
+/// Repos that 404 (renamed, deleted, or private to the token) are left out with a warning.
+fn fetch_listed_repos(http: &dyn Transport, names: &[String]) -> Result<Vec<serde_json::Value>, Error> {
+    let fetched: Vec<Result<Option<serde_json::Value>, Error>> = names
+        .par_iter()
+        .map(|name| {
+            let response = http.get(&format!("https://api.github.com/repos/{}", name))?;
+            if response.status == 404 {
+                eprintln!("warning: leaving out {}: GitHub has no such repo, or the token can't see it", name);
+                return Ok(None);
+            }
+            response.rest_json().map(Some)
+        })
+        .collect();
+    fetched.into_iter().filter_map(Result::transpose).collect()
+}
+
+/// Your repos under `--repos-type` and `--visibility`, or just the `--repo`s and
+/// `--repos-file` names when any are given.
+pub(crate) fn fetch_repos(
+    http: &dyn Transport,
+    config: &Config,
+) -> Result<Vec<serde_json::Value>, Error> {
+    let listed = listed_repos(config)?;
+    if !listed.is_empty() {
+        return fetch_listed_repos(http, &listed);
+    }
+    let mut repos = fetch_repo_pages(http, config)?;
+    if let Some(max_repos) = config.max_repos
+        && repos.len() > max_repos
+    {
+        eprintln!(
+            "warning: --max-repos {} reached; languages only reflect the {} most recently pushed repos",
+            max_repos, max_repos
+        );
+        repos.truncate(max_repos);
+    }
+    Ok(repos)
+}
+
+fn fetch_repo_pages(
+    http: &dyn Transport,
+    config: &Config,
+) -> Result<Vec<serde_json::Value>, Error> {
+    // One page past the cap is enough to tell whether the cap actually cut anything off.
+    let page_limit = config.max_repos.map_or(u32::MAX, |max_repos| (max_repos / 100) as u32 + 1);
+
+    let first = http.get(&repos_page_url(config, 1))?;
+    let mut all_repos = repos_page(config, &first)?;
+    if all_repos.is_empty() {
+        return Ok(all_repos);
+    }
+
+    if let Some(last_page) = first.header("link").and_then(last_page_from_link) {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(config.max_concurrent_pages as usize)
+            .build()
+            .map_err(|e| Error::Config(format!("invalid --max-concurrent-pages: {}", e)))?;
+        let pages: Vec<Result<Vec<serde_json::Value>, Error>> = pool.install(|| {
+            (2..=last_page.min(page_limit))
+                .into_par_iter()
+                .map(|page| {
+                    let response = http.get(&repos_page_url(config, page))?;
+                    repos_page(config, &response)
+                })
+                .collect()
+        });
+        for page in pages {
+            all_repos.append(&mut page?);
+        }
+        return Ok(all_repos);
+    }
+
+    // No `Link` header: walk the pages one by one until GitHub hands back an empty one.
+    let mut page = 2;
+    while page <= page_limit {
+        let response = http.get(&repos_page_url(config, page))?;
+        let mut repos = repos_page(config, &response)?;
+        if repos.is_empty() {
+            break;
+        }
+        all_repos.append(&mut repos);
+        page += 1;
+    }
+    Ok(all_repos)
+}
+
+/// One counted repo's language bytes, and when it was created and last pushed to.
+#[derive(Debug, Clone)]
+pub struct RepoLanguages {
+    pub created_year: Option<i32>,
+    pub pushed_at: Option<DateTime<Utc>>,
+    pub bytes: HashMap<String, u64>,
+}
+
+/// The repos `fetch_repo_languages` counted, and how many it listed and skipped on the way.
+#[derive(Debug, Clone, Default)]
+pub struct RepoSelection {
+    pub repos: Vec<RepoLanguages>,
+    pub listed: usize,
+    /// Skipped as forks, private or tagged; not counting failed language fetches.
+    pub filtered: usize,
+}
+
+/// Why a language chart came out empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoLanguages {
+    /// The listing had no repos at all.
+    NoRepos,
+    /// Every repo was a fork, private, tagged `mirror` or `no-stats`, or excluded by name.
+    AllFiltered,
+    /// No repo's breakdown could be fetched.
+    Unavailable,
+    /// The counted repos hold no code GitHub recognizes.
+    NoCode,
+    /// Every language fell under `--min-lang-bytes`.
+    BelowMinBytes,
+}
+
+impl fmt::Display for NoLanguages {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            NoLanguages::NoRepos => "the token can't see any of your repos",
+            NoLanguages::AllFiltered => {
+                "all your repos matched exclusion filters (forks, private repos without --include-private-langs, `mirror` or `no-stats` topics, --exclude-repo and --exclude-repo-pattern)"
+            }
+            NoLanguages::Unavailable => "none of your repos' language breakdowns could be fetched",
+            NoLanguages::NoCode => "your repos hold no code GitHub recognizes",
+            NoLanguages::BelowMinBytes => "every language is under --min-lang-bytes",
+        })
+    }
+}
+
+impl RepoSelection {
+    /// Why `language_shares` of these repos is empty, or `None` if it isn't.
+    pub fn no_languages(&self, config: &Config, now: DateTime<Utc>) -> Option<NoLanguages> {
+        if !language_shares(&self.repos, config, now).is_empty() {
+            return None;
+        }
+        Some(if self.listed == 0 {
+            NoLanguages::NoRepos
+        } else if self.filtered == self.listed {
+            NoLanguages::AllFiltered
+        } else if self.repos.is_empty() {
+            NoLanguages::Unavailable
+        } else if self.repos.iter().flat_map(|repo| repo.bytes.values()).all(|bytes| *bytes == 0) {
+            NoLanguages::NoCode
+        } else {
+            NoLanguages::BelowMinBytes
+        })
+    }
+}
+
+/// Lists the repos that count toward the language chart (skipping the ones `RepoFilter`
+/// excludes) and fetches each one's language breakdown. Repos whose breakdown can't be
+/// fetched are left out with a warning, or fail the run under `--strict`.
+pub fn fetch_repo_languages(
+    http: &dyn Transport,
+    config: &Config,
+) -> Result<RepoSelection, Error> {
+    let filter = RepoFilter::new(config)?;
+    let all_repos = fetch_repos(http, config)?;
+
+    let fetched: Vec<Result<RepoLanguages, (&str, Error)>> = all_repos
+        .par_iter()
+        .filter_map(|repo| {
+            if filter.excludes(repo) {
+                return None;
+            }
+            let url = repo["languages_url"].as_str()?;
+            let name = repo["full_name"].as_str().unwrap_or(url);
+            let mut bytes = match fetch_languages(http, url) {
+                Ok(bytes) => bytes,
+                Err(problem) => return Some(Err((name, problem))),
+            };
+            apply_language_overrides(&mut bytes, name, config);
+            let created_year = repo["created_at"]
+                .as_str()
+                .and_then(|created| DateTime::parse_from_rfc3339(created).ok())
+                .map(|created| created.year());
+            let pushed_at = repo["pushed_at"]
+                .as_str()
+                .and_then(|pushed| DateTime::parse_from_rfc3339(pushed).ok())
+                .map(|pushed| pushed.with_timezone(&Utc));
+            Some(Ok(RepoLanguages {
+                created_year,
+                pushed_at,
+                bytes,
+            }))
+        })
+        .collect();
+
+    let mut repos = Vec::new();
+    for result in fetched {
+        match result {
+            Ok(repo) => repos.push(repo),
+            // A rejected token fails every other repo too; better to say so than chart nothing.
+            Err((_, problem @ Error::Auth(_))) => return Err(problem),
+            Err((name, problem)) if config.strict => {
+                return Err(Error::Http(format!("languages unavailable for {}: {}", name, problem)));
+            }
+            Err((name, problem)) => eprintln!("warning: leaving out the languages of {}: {}", name, problem),
+        }
+    }
+    Ok(RepoSelection {
+        repos,
+        listed: all_repos.len(),
+        filtered: all_repos.iter().filter(|repo| filter.excludes(repo)).count(),
+    })
+}
+
+/// The repos that stay out of the language chart: forks, private repos unless opted in,
+/// repos tagged `mirror` or `no-stats`, and the `--exclude-repo`s and
+/// `--exclude-repo-pattern`s. Built once per listing, so the patterns compile once.
+pub(crate) struct RepoFilter {
+    include_private: bool,
+    names: Vec<String>,
+    patterns: Vec<Regex>,
+}
+
+impl RepoFilter {
+    pub(crate) fn new(config: &Config) -> Result<Self, Error> {
+        let patterns = config
+            .exclude_repo_patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|e| Error::Config(format!("invalid --exclude-repo-pattern {:?}: {}", pattern, e)))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(RepoFilter {
+            include_private: config.include_private_langs || config.visibility == Visibility::Private,
+            names: config.exclude_repos.clone(),
+            patterns,
+        })
+    }
+
+    /// Whether the repo `full_name` (`owner/repo`) is excluded by name or pattern.
+    pub(crate) fn excludes_name(&self, full_name: &str) -> bool {
+        self.names.iter().any(|excluded| names_repo(excluded, full_name))
+            || self.patterns.iter().any(|pattern| pattern.is_match(full_name))
+    }
+
+    fn excludes(&self, repo: &serde_json::Value) -> bool {
+        let tagged = repo["topics"]
+            .as_array()
+            .is_some_and(|topics| topics.iter().any(|t| t.as_str() == Some("mirror") || t.as_str() == Some("no-stats")));
+        repo["fork"].as_bool().unwrap_or(false)
+            || (repo["private"].as_bool().unwrap_or(false) && !self.include_private)
+            || tagged
+            || repo["full_name"].as_str().is_some_and(|full_name| self.excludes_name(full_name))
+    }
+}
+
+/// Whether `name`, given as `repo` or `owner/repo`, is the repo `full_name`. Either way
+/// case doesn't matter, as on GitHub.
+fn names_repo(name: &str, full_name: &str) -> bool {
+    let repo = full_name.rsplit('/').next().unwrap_or(full_name);
+    name.eq_ignore_ascii_case(full_name) || name.eq_ignore_ascii_case(repo)
+}
+
+/// Applies the `language-overrides` naming the repo `full_name` to its `bytes`.
+pub fn apply_language_overrides(bytes: &mut HashMap<String, u64>, full_name: &str, config: &Config) {
+    let overrides = config.language_overrides.iter().filter(|(name, _)| names_repo(name, full_name));
+    for (language, adjustment) in overrides.flat_map(|(_, adjustments)| adjustments) {
+        let adjusted = bytes.get(language).copied().unwrap_or(0).saturating_add_signed(*adjustment);
+        if adjusted == 0 {
+            bytes.remove(language);
+        } else {
+            bytes.insert(language.clone(), adjusted);
+        }
+    }
+}
+
+/// One repo's language breakdown. An empty object is a real answer (a repo with no code);
+/// an error status (451 for a DMCA takedown, say) or a body that isn't a byte map is not.
+fn fetch_languages(http: &dyn Transport, url: &str) -> Result<HashMap<String, u64>, Error> {
+    let response = http.get(url)?;
+    if let Some(error) = response.rejected_token() {
+        return Err(error);
+    }
+    if let Some(error) = response.github_error() {
+        return Err(Error::Http(error));
+    }
+    response.json().map_err(|e| Error::Http(format!("unexpected response body ({})", e)))
+}
+
+/// The top eight languages by share of all bytes, as percentages. With
+/// `--lang-recency-halflife`, each repo's bytes are first decayed by how long ago it was
+/// pushed, halving every half-life. Languages totalling less than `--min-lang-bytes` are
+/// dropped before the shares are computed, so the rest still add up to 100%.
+pub fn language_shares(repos: &[RepoLanguages], config: &Config, now: DateTime<Utc>) -> Vec<(String, f64)> {
+    let mut languages = merge_language_maps(repos.iter().map(|repo| match config.lang_recency_halflife {
+        Some(halflife) => decay_bytes(repo, halflife, now),
+        None => repo.bytes.clone(),
+    }));
+    languages.retain(|_, bytes| *bytes >= config.min_lang_bytes);
+
+    let total_bytes: u64 = languages.values().sum();
+    if total_bytes == 0 {
+        return Vec::new();
+    }
+
+    let language_percentages: Vec<(String, f64)> = languages
+        .into_iter()
+        .map(|(lang, count)| (lang, (count as f64 / total_bytes as f64) * 100.0))
+        .collect();
+
+    top_languages(language_percentages, config)
+}
+
+/// Scales a repo's bytes by `decay_factor`.
+fn decay_bytes(repo: &RepoLanguages, halflife_days: f64, now: DateTime<Utc>) -> HashMap<String, u64> {
+    let factor = decay_factor(repo.pushed_at, halflife_days, now);
+    repo.bytes
+        .iter()
+        .map(|(lang, bytes)| (lang.clone(), (*bytes as f64 * factor).round() as u64))
+        .collect()
+}
+
+/// `0.5^(days since push / halflife)`. Repos without a push date count in full.
+fn decay_factor(pushed_at: Option<DateTime<Utc>>, halflife_days: f64, now: DateTime<Utc>) -> f64 {
+    match pushed_at {
+        Some(pushed_at) => {
+            let age_days = (now - pushed_at).num_seconds().max(0) as f64 / 86_400.0;
+            0.5f64.powf(age_days / halflife_days)
+        }
+        None => 1.0,
+    }
+}
+
+/// One repo's primary language, as `--lang-metric repo-count` counts it.
+#[derive(Debug, Clone)]
+pub struct RepoPrimaryLanguage {
+    pub language: Option<String>,
+    pub pushed_at: Option<DateTime<Utc>>,
+}
+
+/// The top eight languages by share of repos they're the primary language of, as
+/// percentages. Repos without one are skipped. With `--lang-recency-halflife`, each repo's
+/// vote decays like its bytes would.
+pub fn repo_count_shares(repos: &[RepoPrimaryLanguage], config: &Config, now: DateTime<Utc>) -> Vec<(String, f64)> {
+    let mut counts: BTreeMap<String, f64> = BTreeMap::new();
+    for repo in repos {
+        let Some(language) = &repo.language else {
+            continue;
+        };
+        let name = canonical_language(language).map(String::from).unwrap_or_else(|| language.clone());
+        let weight = config.lang_recency_halflife.map_or(1.0, |halflife| decay_factor(repo.pushed_at, halflife, now));
+        *counts.entry(name).or_insert(0.0) += weight;
+    }
+
+    let total: f64 = counts.values().sum();
+    if total <= 0.0 {
+        return Vec::new();
+    }
+    let shares: Vec<(String, f64)> = counts.into_iter().map(|(lang, count)| (lang, count / total * 100.0)).collect();
+    top_languages(shares, config)
+}
+
+/// Why a `repo_count_shares` chart of `repos` came out empty.
+pub fn no_primary_languages(repos: &[RepoPrimaryLanguage]) -> NoLanguages {
+    if repos.is_empty() { NoLanguages::NoRepos } else { NoLanguages::NoCode }
+}
+
+/// The language chart, and why it's empty if it is.
+#[derive(Debug, Clone)]
+pub struct LanguageStats {
+    pub languages: Vec<(String, f64)>,
+    pub empty_reason: Option<NoLanguages>,
+    /// See `estimate_lines`. Only with `--estimate-lines`.
+    pub estimated_lines: Option<u64>,
+}
+
+/// Typical bytes per line of code, by Linguist name, for `--estimate-lines`. Languages not
+/// listed (or in `bytes-per-line`) get `DEFAULT_BYTES_PER_LINE`.
+const BYTES_PER_LINE: &[(&str, f64)] = &[
+    ("C", 28.0),
+    ("C#", 34.0),
+    ("C++", 30.0),
+    ("CSS", 24.0),
+    ("Go", 27.0),
+    ("HTML", 45.0),
+    ("Java", 36.0),
+    ("JavaScript", 32.0),
+    ("Jupyter Notebook", 120.0),
+    ("Kotlin", 34.0),
+    ("Python", 32.0),
+    ("Ruby", 26.0),
+    ("Rust", 33.0),
+    ("Shell", 30.0),
+    ("Swift", 33.0),
+    ("TypeScript", 34.0),
+];
+
+const DEFAULT_BYTES_PER_LINE: f64 = 35.0;
+
+/// Roughly how many lines of code `repos` hold: each language's bytes (less any under
+/// `--min-lang-bytes`, as on the chart) over its bytes per line.
+pub fn estimate_lines(repos: &[RepoLanguages], config: &Config) -> u64 {
+    let languages = merge_language_maps(repos.iter().map(|repo| repo.bytes.clone()));
+    let lines: f64 = languages
+        .iter()
+        .filter(|(_, bytes)| **bytes >= config.min_lang_bytes)
+        .map(|(lang, bytes)| {
+            let per_line = config
+                .bytes_per_line
+                .get(lang)
+                .copied()
+                .or_else(|| BYTES_PER_LINE.iter().find(|(name, _)| name == lang).map(|(_, per_line)| *per_line))
+                .unwrap_or(DEFAULT_BYTES_PER_LINE);
+            *bytes as f64 / per_line
+        })
+        .sum();
+    lines.round() as u64
+}
+
+pub fn calculate_language_stats(
+    http: &dyn Transport,
+    _username: &str,
+    config: &Config,
+    now: DateTime<Utc>,
+) -> Result<LanguageStats, Error> {
+    if config.lang_metric == LangMetric::RepoCount {
+        // The listing already names each repo's primary language; no per-repo requests.
+        let filter = RepoFilter::new(config)?;
+        let repos: Vec<RepoPrimaryLanguage> = fetch_repos(http, config)?
+            .iter()
+            .filter(|repo| !filter.excludes(repo))
+            .map(|repo| RepoPrimaryLanguage {
+                language: repo["language"].as_str().map(String::from),
+                pushed_at: repo["pushed_at"]
+                    .as_str()
+                    .and_then(|pushed| DateTime::parse_from_rfc3339(pushed).ok())
+                    .map(|pushed| pushed.with_timezone(&Utc)),
+            })
+            .collect();
+        let languages = repo_count_shares(&repos, config, now);
+        let empty_reason = languages.is_empty().then(|| no_primary_languages(&repos));
+        return Ok(LanguageStats { languages, empty_reason, estimated_lines: None });
+    }
+    let selection = fetch_repo_languages(http, config)?;
+    Ok(LanguageStats {
+        languages: language_shares(&selection.repos, config, now),
+        empty_reason: selection.no_languages(config, now),
+        estimated_lines: config.estimate_lines.then(|| estimate_lines(&selection.repos, config)),
+    })
+}
+
+/// Approximates the dominant language of each year: every repo's current bytes are credited
+/// to the year it was created, and the language with the most bytes wins that year. Repos
+/// keep growing after their first year, so this reflects what was started each year rather
+/// than what was written. Years without repos, or where the top two languages tie, are left
+/// out.
+pub fn language_timeline(repos: &[RepoLanguages]) -> BTreeMap<i32, String> {
+    let mut by_year: BTreeMap<i32, Vec<HashMap<String, u64>>> = BTreeMap::new();
+    for repo in repos {
+        if let Some(year) = repo.created_year {
+            by_year.entry(year).or_default().push(repo.bytes.clone());
+        }
+    }
+
+    by_year
+        .into_iter()
+        .filter_map(|(year, maps)| {
+            let mut totals: Vec<(String, u64)> = merge_language_maps(maps).into_iter().collect();
+            totals.sort_by_key(|(_, bytes)| std::cmp::Reverse(*bytes));
+            match totals.as_slice() {
+                [(_, top), (_, second), ..] if top == second => None,
+                [(language, bytes), ..] if *bytes > 0 => Some((year, language.clone())),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// The eight largest `shares`, with the rest as `Other` under `--lang-other`.
+fn top_languages(mut shares: Vec<(String, f64)>, config: &Config) -> Vec<(String, f64)> {
+    sort_by_share(&mut shares);
+    let rest: f64 = shares.drain(shares.len().min(8)..).map(|(_, share)| share).sum();
+    if config.lang_other && rest > 0.0 {
+        shares.push(("Other".to_string(), rest));
+    }
+    shares
+}
+
+/// How far the charted percentages may stray from 100 under `--strict-languages-sum`.
+const SUM_TOLERANCE: f64 = 0.5;
+
+/// Checks that `languages` add up to 100%, for `--strict-languages-sum`. An empty chart
+/// passes; it says why it's empty instead.
+pub fn check_languages_sum(languages: &[(String, f64)]) -> Result<(), Error> {
+    let sum: f64 = languages.iter().map(|(_, share)| share).sum();
+    if languages.is_empty() || (sum - 100.0).abs() <= SUM_TOLERANCE {
+        return Ok(());
+    }
+    Err(Error::Config(format!(
+        "the charted language percentages add up to {:.2}%, not 100% (--lang-other keeps the languages past the top eight)",
+        sum
+    )))
+}
+
+/// Orders languages by descending share, breaking ties alphabetically so equal byte counts
+/// never swap places between runs.
+pub fn sort_by_share(languages: &mut [(String, f64)]) {
+    languages.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+}
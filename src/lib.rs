@@ -0,0 +1,22 @@
+pub mod auth;
+pub mod cache;
+pub mod changes;
+pub mod colors;
+pub mod config;
+pub mod doctor;
+pub mod dump;
+pub mod error;
+pub mod github;
+pub mod history;
+pub mod http;
+pub mod languages;
+pub mod lint;
+pub mod metrics;
+pub mod oneline;
+pub mod raster;
+pub mod render;
+pub mod serve;
+pub mod shields;
+pub mod stats;
+pub mod tui;
+pub mod validate;
@@ -0,0 +1,157 @@
+use crate::config::StatCategory;
+use crate::render::CONTEXT_VARIABLES;
+use clap::ValueEnum;
+use std::collections::BTreeSet;
+use std::fmt;
+use tera::Tera;
+use tera::ast::{Expr, ExprVal, Node};
+
+/// What `--template-lint` found in one template, compared with the variables `build_context`
+/// can provide.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintReport {
+    pub template: String,
+    /// Referenced but never provided, so they fail to render (or are always falsy in an `if`).
+    pub undefined: BTreeSet<String>,
+    /// Provided but never referenced.
+    pub unused: BTreeSet<String>,
+}
+
+impl fmt::Display for LintReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |names: &BTreeSet<String>| {
+            if names.is_empty() {
+                "none".to_string()
+            } else {
+                names.iter().cloned().collect::<Vec<_>>().join(", ")
+            }
+        };
+        writeln!(f, "{}:", self.template)?;
+        writeln!(f, "  undefined: {}", list(&self.undefined))?;
+        write!(f, "  unused: {}", list(&self.unused))
+    }
+}
+
+/// Everything a template may reference: the context variables plus the `has_<category>` flags.
+fn known_variables() -> BTreeSet<String> {
+    CONTEXT_VARIABLES
+        .iter()
+        .map(|name| name.to_string())
+        .chain(StatCategory::value_variants().iter().map(|category| category.flag()))
+        .collect()
+}
+
+/// Lints every loaded template, in name order.
+pub fn lint_templates(tera: &Tera) -> tera::Result<Vec<LintReport>> {
+    let mut names: Vec<&str> = tera.get_template_names().collect();
+    names.sort();
+    names.into_iter().map(|name| lint_template(tera, name)).collect()
+}
+
+/// Walks the parsed template for the variables it reads. Loop variables, `set` targets and
+/// macro arguments are local and don't count.
+pub fn lint_template(tera: &Tera, name: &str) -> tera::Result<LintReport> {
+    let template = tera.get_template(name)?;
+    let mut walker = Walker::default();
+    walker.nodes(&template.ast, &[]);
+    for definition in template.macros.values() {
+        let scope: Vec<String> = definition.args.keys().cloned().collect();
+        walker.nodes(&definition.body, &scope);
+    }
+
+    let known = known_variables();
+    Ok(LintReport {
+        template: name.to_string(),
+        undefined: walker.referenced.difference(&known).cloned().collect(),
+        unused: known.difference(&walker.referenced).cloned().collect(),
+    })
+}
+
+#[derive(Default)]
+struct Walker {
+    referenced: BTreeSet<String>,
+}
+
+impl Walker {
+    fn nodes(&mut self, nodes: &[Node], scope: &[String]) {
+        let mut scope = scope.to_vec();
+        for node in nodes {
+            match node {
+                Node::VariableBlock(_, expr) => self.expr(expr, &scope),
+                Node::Set(_, set) => {
+                    self.expr(&set.value, &scope);
+                    scope.push(set.key.clone());
+                }
+                Node::FilterSection(_, section, _) => {
+                    section.filter.args.values().for_each(|arg| self.expr(arg, &scope));
+                    self.nodes(&section.body, &scope);
+                }
+                Node::Block(_, block, _) => self.nodes(&block.body, &scope),
+                Node::Forloop(_, forloop, _) => {
+                    self.expr(&forloop.container, &scope);
+                    let mut body_scope = scope.clone();
+                    body_scope.extend(forloop.key.iter().cloned());
+                    body_scope.push(forloop.value.clone());
+                    body_scope.push("loop".to_string());
+                    self.nodes(&forloop.body, &body_scope);
+                    if let Some(empty_body) = &forloop.empty_body {
+                        self.nodes(empty_body, &scope);
+                    }
+                }
+                Node::If(branches, _) => {
+                    for (_, condition, body) in &branches.conditions {
+                        self.expr(condition, &scope);
+                        self.nodes(body, &scope);
+                    }
+                    if let Some((_, body)) = &branches.otherwise {
+                        self.nodes(body, &scope);
+                    }
+                }
+                // Macro bodies are walked from the template's macro table.
+                _ => {}
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr, scope: &[String]) {
+        self.value(&expr.val, scope);
+        for filter in &expr.filters {
+            filter.args.values().for_each(|arg| self.expr(arg, scope));
+        }
+    }
+
+    fn value(&mut self, value: &ExprVal, scope: &[String]) {
+        match value {
+            ExprVal::Ident(ident) => self.reference(ident, scope),
+            ExprVal::Test(test) => {
+                self.reference(&test.ident, scope);
+                test.args.iter().for_each(|arg| self.expr(arg, scope));
+            }
+            ExprVal::Math(math) => {
+                self.expr(&math.lhs, scope);
+                self.expr(&math.rhs, scope);
+            }
+            ExprVal::Logic(logic) => {
+                self.expr(&logic.lhs, scope);
+                self.expr(&logic.rhs, scope);
+            }
+            ExprVal::In(contains) => {
+                self.expr(&contains.lhs, scope);
+                self.expr(&contains.rhs, scope);
+            }
+            ExprVal::MacroCall(call) => call.args.values().for_each(|arg| self.expr(arg, scope)),
+            ExprVal::FunctionCall(call) => call.args.values().for_each(|arg| self.expr(arg, scope)),
+            ExprVal::Array(items) => items.iter().for_each(|item| self.expr(item, scope)),
+            ExprVal::StringConcat(concat) => concat.values.iter().for_each(|value| self.value(value, scope)),
+            ExprVal::String(_) | ExprVal::Int(_) | ExprVal::Float(_) | ExprVal::Bool(_) => {}
+        }
+    }
+
+    /// Records the root of `lang.name` or `extra["bio"]`, unless it's a local.
+    fn reference(&mut self, ident: &str, scope: &[String]) {
+        let root = ident.split(['.', '[']).next().unwrap_or(ident);
+        if root != "__tera_context" && !scope.iter().any(|local| local == root) {
+            self.referenced.insert(root.to_string());
+        }
+    }
+}
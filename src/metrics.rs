@@ -0,0 +1,122 @@
+use crate::config::{Config, LangMetric};
+use crate::history::HistoryEntry;
+use crate::stats::Stats;
+use std::fmt::Write;
+
+/// Renders the raw (unabbreviated) stats in the Prometheus text exposition format.
+pub fn render_prometheus(stats: &Stats, config: &Config) -> String {
+    let user = escape_label(&stats.username);
+    let mut out = String::new();
+
+    let mut gauge = |name: &str, help: &str, value: u64| {
+        writeln!(out, "# HELP {} {}", name, help).unwrap();
+        writeln!(out, "# TYPE {} gauge", name).unwrap();
+        writeln!(out, "{}{{user=\"{}\"}} {}", name, user, value).unwrap();
+    };
+    gauge("github_stats_stars", "Stars received across owned repositories.", stats.total_stars);
+    gauge("github_stats_stars_given", "Repositories the user has starred.", stats.stars_given);
+    if let Some(commits) = stats.total_commits_this_year {
+        gauge("github_stats_commits_year", "Commit contributions in the --window: this calendar year, or the last 365 days.", commits);
+    }
+    gauge("github_stats_prs", "Pull requests opened, all time.", stats.total_prs);
+    gauge("github_stats_prs_year", "Pull requests opened in the --window: this calendar year, or the last 365 days.", stats.prs_this_year);
+    gauge("github_stats_issues", "Issues opened, all time.", stats.total_issues);
+    gauge("github_stats_issues_year", "Issues opened in the --window: this calendar year, or the last 365 days.", stats.issues_this_year);
+    if let Some(contributed_to) = stats.contributed_to {
+        gauge("github_stats_contributed_to", "Repositories contributed to.", contributed_to);
+    }
+    if let Some(all_time) = &stats.all_time {
+        gauge("github_stats_commits_all_time", "Commit contributions since the account was created.", all_time.total_commits);
+    }
+
+    if !stats.languages.is_empty() {
+        writeln!(out, "# HELP github_stats_language_percent {}", language_help(config)).unwrap();
+        writeln!(out, "# TYPE github_stats_language_percent gauge").unwrap();
+        for (lang, percentage) in &stats.languages {
+            writeln!(
+                out,
+                "github_stats_language_percent{{user=\"{}\",lang=\"{}\"}} {}",
+                user,
+                escape_label(lang),
+                percentage
+            )
+            .unwrap();
+        }
+    }
+    out
+}
+
+/// What the language gauge's percentages are a share of, per `--lang-metric` and
+/// `--lang-recency-halflife`.
+fn language_help(config: &Config) -> String {
+    let share = match config.lang_metric {
+        LangMetric::Bytes => "Share of code bytes per language",
+        LangMetric::RepoCount => "Share of repos per language, counting each repo's primary language",
+    };
+    let decay = if config.lang_recency_halflife.is_some() { ", weighted toward recently pushed repos" } else { "" };
+    format!("{}{}.", share, decay)
+}
+
+type Sample = fn(&HistoryEntry) -> Option<u64>;
+
+/// The gauges a `--history-file` line can replay, named as in `render_prometheus`.
+const HISTORY_GAUGES: [(&str, &str, Sample); 6] = [
+    ("github_stats_stars", "Stars received across owned repositories.", |entry| Some(entry.total_stars)),
+    ("github_stats_stars_given", "Repositories the user has starred.", |entry| Some(entry.stars_given)),
+    ("github_stats_commits_year", "Commit contributions in the --window: this calendar year, or the last 365 days.", |entry| entry.total_commits_this_year),
+    ("github_stats_prs", "Pull requests opened, all time.", |entry| Some(entry.total_prs)),
+    ("github_stats_issues", "Issues opened, all time.", |entry| Some(entry.total_issues)),
+    ("github_stats_contributed_to", "Repositories contributed to.", |entry| entry.contributed_to),
+];
+
+/// Renders `entries` (the `--history-file`, then this run) in the OpenMetrics text format,
+/// one timestamped sample per entry, for backfilling a TSDB with `promtool tsdb
+/// create-blocks-from openmetrics`. Timestamps are seconds since the epoch with millisecond
+/// precision, as OpenMetrics has them. Languages are the top three each entry recorded.
+pub fn render_openmetrics(username: &str, entries: &[HistoryEntry], config: &Config) -> String {
+    let user = escape_label(username);
+    let timestamp = |entry: &HistoryEntry| format!("{:.3}", entry.timestamp.timestamp_millis() as f64 / 1000.0);
+    let mut out = String::new();
+
+    for (name, help, sample) in HISTORY_GAUGES {
+        let samples: Vec<(&HistoryEntry, u64)> = entries.iter().filter_map(|entry| Some((entry, sample(entry)?))).collect();
+        if samples.is_empty() {
+            continue;
+        }
+        writeln!(out, "# TYPE {} gauge", name).unwrap();
+        writeln!(out, "# HELP {} {}", name, help).unwrap();
+        for (entry, value) in samples {
+            writeln!(out, "{}{{user=\"{}\"}} {} {}", name, user, value, timestamp(entry)).unwrap();
+        }
+    }
+
+    if entries.iter().any(|entry| !entry.top_languages.is_empty()) {
+        writeln!(out, "# TYPE github_stats_language_percent gauge").unwrap();
+        writeln!(out, "# HELP github_stats_language_percent {}", language_help(config)).unwrap();
+        // OpenMetrics wants each series' samples together and in time order.
+        let mut languages: Vec<&str> = entries.iter().flat_map(|entry| &entry.top_languages).map(|(lang, _)| lang.as_str()).collect();
+        languages.sort_unstable();
+        languages.dedup();
+        for lang in languages {
+            for entry in entries {
+                if let Some((_, percentage)) = entry.top_languages.iter().find(|(name, _)| name == lang) {
+                    writeln!(
+                        out,
+                        "github_stats_language_percent{{user=\"{}\",lang=\"{}\"}} {} {}",
+                        user,
+                        escape_label(lang),
+                        percentage,
+                        timestamp(entry)
+                    )
+                    .unwrap();
+                }
+            }
+        }
+    }
+    out.push_str("# EOF\n");
+    out
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', r"\\").replace('"', "\\\"").replace('\n', r"\n")
+}
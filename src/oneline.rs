@@ -0,0 +1,56 @@
+use crate::config::Config;
+use crate::render::abbreviate_number;
+use crate::stats::Stats;
+
+pub const DEFAULT_ONELINE_FORMAT: &str = "★{stars} ⑂{contributed} PR:{prs} Issues:{issues}";
+
+/// Fills `{placeholder}`s in a one-line format string; `{{` and `}}` give literal braces.
+pub fn render_oneline(stats: &Stats, format: &str, config: &Config) -> Result<String, String> {
+    let mut out = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                out.push_str(&placeholder(stats, &name, config)?);
+            }
+            _ => out.push(c),
+        }
+    }
+    out.push('\n');
+    Ok(out)
+}
+
+fn placeholder(stats: &Stats, name: &str, config: &Config) -> Result<String, String> {
+    let value = match name {
+        "user" => return Ok(stats.username.clone()),
+        "top_language" => return Ok(stats.languages.first().map(|(lang, _)| lang.clone()).unwrap_or_default()),
+        "stars" => stats.total_stars,
+        "stars_given" => stats.stars_given,
+        "commits" => return Ok(available(stats.total_commits_this_year, config)),
+        "prs" => stats.total_prs,
+        "prs_year" => stats.prs_this_year,
+        "issues" => stats.total_issues,
+        "issues_year" => stats.issues_this_year,
+        "contributed" => return Ok(available(stats.contributed_to, config)),
+        "commits_all_time" => match &stats.all_time {
+            Some(all_time) => all_time.total_commits,
+            None => return Err("{commits_all_time} needs --all-time".to_string()),
+        },
+        _ => return Err(format!("unknown placeholder {{{}}} in --oneline-format", name)),
+    };
+    Ok(abbreviate_number(value, config))
+}
+
+/// Counts the REST fallback couldn't provide show as `n/a`, keeping the line's shape.
+fn available(value: Option<u64>, config: &Config) -> String {
+    value.map(|n| abbreviate_number(n, config)).unwrap_or_else(|| "n/a".to_string())
+}
@@ -0,0 +1,38 @@
+use crate::error::Error;
+use resvg::{tiny_skia, usvg};
+use std::sync::Arc;
+
+/// DejaVu Sans ships inside the binary so a card rasterizes to the same pixels on every
+/// machine, whatever fonts happen to be installed.
+const FONTS: [&[u8]; 2] = [
+    include_bytes!("../assets/fonts/DejaVuSans.ttf"),
+    include_bytes!("../assets/fonts/DejaVuSans-Bold.ttf"),
+];
+const FONT_FAMILY: &str = "DejaVu Sans";
+
+pub fn rasterize(svg: &str, scale: f32) -> Result<Vec<u8>, Error> {
+    let mut fontdb = usvg::fontdb::Database::new();
+    for font in FONTS {
+        fontdb.load_font_data(font.to_vec());
+    }
+    // Any family the SVG asks for that we don't bundle falls back to DejaVu.
+    fontdb.set_serif_family(FONT_FAMILY);
+    fontdb.set_sans_serif_family(FONT_FAMILY);
+
+    let options = usvg::Options {
+        font_family: FONT_FAMILY.to_string(),
+        fontdb: Arc::new(fontdb),
+        ..Default::default()
+    };
+    let tree = usvg::Tree::from_str(svg, &options).map_err(|e| Error::Raster(format!("invalid SVG: {}", e)))?;
+
+    let size = tree
+        .size()
+        .to_int_size()
+        .scale_by(scale)
+        .ok_or_else(|| Error::Raster("PNG scale produces an empty image".to_string()))?;
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| Error::Raster("PNG dimensions are invalid".to_string()))?;
+    resvg::render(&tree, tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+    pixmap.encode_png().map_err(|e| Error::Raster(format!("PNG encoding failed: {}", e)))
+}
@@ -0,0 +1,899 @@
+use crate::colors::{FALLBACK_COLOR, language_color, nearest_ansi256};
+use crate::config::{BarDirection, BarScale, CommitMode, Config, Format, LineEndings, SkipField, StatCategory};
+use crate::error::Error;
+use crate::history::{HistoryEntry, LanguageTrend, read_history};
+use crate::metrics::{render_openmetrics, render_prometheus};
+use crate::oneline::render_oneline;
+use crate::raster::rasterize;
+use crate::shields::render_shields;
+use crate::stats::Stats;
+use chrono::Utc;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tera::{Context, Tera, Value};
+
+pub const TEMPLATE_GLOB: &str = "templates/**/*.tera";
+
+/// The name `--template-string` is loaded under. Without a markup extension, nothing in it
+/// is escaped.
+pub const TEMPLATE_STRING_NAME: &str = "template-string";
+
+/// Templates whose output is markup. Tera only escapes `.html`, `.htm` and `.xml` names by
+/// default, which misses `stats.svg.tera`; a stray `&` or `<` in a value would break the card.
+const AUTOESCAPE_SUFFIXES: &[&str] = &[".svg.tera", ".html.tera", ".htm.tera", ".xml.tera", ".svg", ".html", ".htm", ".xml"];
+
+/// Loads the templates under `glob`, HTML-escaping values in the markup ones unless
+/// `--no-autoescape` is set.
+///
+/// Besides Tera's own, templates get the filters `or_zero`, `or_dash`, `sparkline`,
+/// `human_bytes` and `group` (thousands separated by commas), and these functions, all
+/// taking named arguments:
+///
+/// - `add(a, b)`, `sub(a, b)`, `mul(a, b)`, `div(a, b)`, `max(a, b)` and `min(a, b)`
+/// - `percent_of(part, whole)`: `part` as a percentage of `whole`, 0 when `whole` is 0
+/// - `color_for_language(name)`: the language's hex color, or the fallback gray
+///
+/// The arithmetic works on numbers, not the abbreviated strings (`1.2k`) most counts are
+/// inserted as; `{{ percent_of(part=lang.percentage, whole=top_language_percentage) }}`.
+///
+/// With `--template-dir`s, a template comes from the first directory holding one of that
+/// name, then the next, and the built-ins under `glob` last. Names are paths relative to
+/// their directory, so `svg/stats.svg.tera` only overrides `svg/stats.svg.tera`. A
+/// `--template-file` is added last, under its file name, or a `--template-string` as
+/// `TEMPLATE_STRING_NAME`. A name found in two `--template-dir`s, or in one and as the
+/// `--template-file`, is warned about (an error under `--strict`); shadowing a built-in
+/// is what the directories are for, and isn't.
+pub fn load_templates(glob: &str, config: &Config) -> tera::Result<Tera> {
+    let mut tera = Tera::new(glob)?;
+    let mut overrides: Vec<(PathBuf, Option<String>)> = Vec::new();
+    let mut shadowed = Vec::new();
+    for dir in &config.template_dirs {
+        if !dir.is_dir() {
+            return Err(tera::Error::msg(format!("--template-dir {} is not a directory", dir.display())));
+        }
+        for (path, name) in template_files(dir)? {
+            match overrides.iter().find(|(_, seen)| seen.as_deref() == Some(name.as_str())) {
+                Some((used, _)) => shadowed.push(format!("{} is used as {}, not {}", used.display(), name, path.display())),
+                None => overrides.push((path, Some(name))),
+            }
+        }
+    }
+    if let (Some(path), Some(name)) = (&config.template_file, config.template_file_name())
+        && let Some((unused, _)) = overrides.iter().find(|(_, seen)| seen.as_deref() == Some(name.as_str()))
+    {
+        shadowed.push(format!("{} is used as {}, not {}", path.display(), name, unused.display()));
+    }
+    // Two files under one name is usually a mistake: edits to the unused one go nowhere.
+    if config.strict && !shadowed.is_empty() {
+        return Err(tera::Error::msg(format!("duplicate template names: {}", shadowed.join("; "))));
+    }
+    for duplicate in &shadowed {
+        eprintln!("warning: duplicate template name: {}", duplicate);
+    }
+    // All at once, so an override may extend a template from a later directory.
+    tera.add_template_files(overrides)?;
+    if let (Some(path), Some(name)) = (&config.template_file, config.template_file_name()) {
+        tera.add_template_file(path, Some(&name))?;
+    }
+    if let Some(source) = &config.template_string {
+        tera.add_raw_template(TEMPLATE_STRING_NAME, source)?;
+    }
+    if config.no_autoescape {
+        tera.autoescape_on(Vec::new());
+    } else {
+        tera.autoescape_on(AUTOESCAPE_SUFFIXES.to_vec());
+    }
+    tera.register_filter("or_zero", or_zero);
+    tera.register_filter("or_dash", or_dash);
+    tera.register_filter("sparkline", sparkline);
+    tera.register_filter("human_bytes", human_bytes_filter);
+    tera.register_filter("group", group_filter);
+    for (name, op) in ARITHMETIC {
+        tera.register_function(name, move |args: &HashMap<String, Value>| {
+            let (a, b) = (number_arg(args, name, "a")?, number_arg(args, name, "b")?);
+            op(a, b).map(number_value).ok_or_else(|| tera::Error::msg(format!("{}: b can't be zero", name)))
+        });
+    }
+    tera.register_function("percent_of", percent_of);
+    tera.register_function("color_for_language", color_for_language);
+    Ok(tera)
+}
+
+type Arithmetic = fn(f64, f64) -> Option<f64>;
+
+const ARITHMETIC: [(&str, Arithmetic); 6] = [
+    ("add", |a, b| Some(a + b)),
+    ("sub", |a, b| Some(a - b)),
+    ("mul", |a, b| Some(a * b)),
+    ("div", |a, b| (b != 0.0).then(|| a / b)),
+    ("max", |a, b| Some(a.max(b))),
+    ("min", |a, b| Some(a.min(b))),
+];
+
+fn number_arg(args: &HashMap<String, Value>, function: &str, name: &str) -> tera::Result<f64> {
+    let value = args.get(name).ok_or_else(|| tera::Error::msg(format!("{} needs a `{}` argument", function, name)))?;
+    value
+        .as_f64()
+        .ok_or_else(|| tera::Error::msg(format!("{}: `{}` must be a number, got {}", function, name, value)))
+}
+
+/// Whole results come back as integers, so `add(a=1, b=2)` renders `3` rather than `3.0`.
+fn number_value(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < i64::MAX as f64 { Value::from(n as i64) } else { Value::from(n) }
+}
+
+/// `{{ percent_of(part=..., whole=...) }}`.
+fn percent_of(args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let (part, whole) = (number_arg(args, "percent_of", "part")?, number_arg(args, "percent_of", "whole")?);
+    Ok(number_value(if whole == 0.0 { 0.0 } else { part / whole * 100.0 }))
+}
+
+/// `{{ color_for_language(name=lang.name) }}`. Takes the display names in `languages` too,
+/// padding and shortenings (`Jupyter`, `VB.NET`) included.
+fn color_for_language(args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let name = args
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| tera::Error::msg("color_for_language needs a string `name` argument"))?
+        .trim();
+    let name = match name {
+        "VB.NET" => "Visual Basic .NET",
+        "Jupyter" => "Jupyter Notebook",
+        name => name,
+    };
+    Ok(Value::from(language_color(name).unwrap_or(FALLBACK_COLOR)))
+}
+
+/// The `.tera` files under `dir`, named by their `/`-separated path relative to it, the way
+/// Tera names globbed templates.
+fn template_files(dir: &Path) -> tera::Result<Vec<(PathBuf, String)>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let entries = fs::read_dir(&current).map_err(|e| tera::Error::msg(format!("failed to read {}: {}", current.display(), e)))?;
+        for entry in entries {
+            let path = entry.map_err(|e| tera::Error::msg(e.to_string()))?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "tera") {
+                let relative = path.strip_prefix(dir).unwrap_or(&path);
+                let name = relative.components().map(|part| part.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+                files.push((path, name));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// `{{ value | or_zero }}`: a null stat renders as 0.
+fn or_zero(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    Ok(if value.is_null() { Value::from(0) } else { value.clone() })
+}
+
+/// `{{ value | or_dash }}`: a null stat renders as a dash.
+fn or_dash(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    Ok(if value.is_null() { Value::from("-") } else { value.clone() })
+}
+
+/// `{{ stars_history | sparkline }}`: a number array as block characters from `▁` at its
+/// minimum to `█` at its maximum. A flat series sits at `▁`.
+fn sparkline(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let points = value
+        .as_array()
+        .ok_or_else(|| tera::Error::msg("sparkline expects an array of numbers"))?
+        .iter()
+        .map(|point| point.as_f64().ok_or_else(|| tera::Error::msg(format!("sparkline got a non-number: {}", point))))
+        .collect::<tera::Result<Vec<f64>>>()?;
+    let min = points.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = points.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let line: String = points
+        .iter()
+        .map(|point| {
+            let level = if max > min { (point - min) / (max - min) * 7.0 } else { 0.0 };
+            BLOCKS[level.round() as usize]
+        })
+        .collect();
+    Ok(Value::from(line))
+}
+
+/// `{{ total_disk_usage | human_bytes }}`: a byte count as `450 MB`.
+fn human_bytes_filter(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let bytes = value.as_u64().ok_or_else(|| tera::Error::msg(format!("human_bytes expects a byte count, got {}", value)))?;
+    Ok(Value::from(human_bytes(bytes)))
+}
+
+/// `{{ estimated_lines | group }}`: a whole number with its thousands separated by commas.
+fn group_filter(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let n = value.as_u64().ok_or_else(|| tera::Error::msg(format!("group expects a whole number, got {}", value)))?;
+    Ok(Value::from(group_digits(n)))
+}
+
+/// `1234567` as `1,234,567`.
+pub fn group_digits(n: u64) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Formats a byte count in binary units, keeping one decimal below 10: `512 B`, `1.5 KB`,
+/// `450 MB`.
+pub fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 || size >= 10.0 {
+        format!("{:.0} {}", size, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// `n` as `1.2k` or `3.4M`, or in full below `--abbreviate-threshold` (digits grouped
+/// with `--group-digits`) or when it would round to `0.0k`. The unit goes by the rounded
+/// value, so 999,950 reads `1.0M` rather than `1000.0k`.
+pub fn abbreviate_number(n: u64, config: &Config) -> String {
+    let tenths_of_k = (n as f64 / 100.0).round();
+    if n < config.abbreviate_threshold || tenths_of_k == 0.0 {
+        if config.group_digits { group_digits(n) } else { n.to_string() }
+    } else if tenths_of_k >= 10_000.0 {
+        format!("{:.1}M", (n as f64) / 1_000_000.0)
+    } else {
+        format!("{:.1}k", (n as f64) / 1000.0)
+    }
+}
+
+/// Draws a ten-cell bar for `percentage`, where `full_scale` is the percentage that fills it.
+/// `Rtl` puts the empty cells first. With an `ansi` color (0–255), the filled cells are
+/// wrapped in its escape codes.
+pub fn render_progress_bar(percentage: f64, full_scale: f64, direction: BarDirection, ansi: Option<u8>) -> String {
+    let full_scale = if full_scale > 0.0 { full_scale } else { 100.0 };
+    let num_filled = (percentage / full_scale * 10.0).round().max(0.0) as usize;
+    let num_empty = 10usize.saturating_sub(num_filled);
+    let mut filled = "▓".repeat(num_filled);
+    if let Some(color) = ansi.filter(|_| num_filled > 0) {
+        filled = format!("\x1b[38;5;{}m{}\x1b[0m", color, filled);
+    }
+    let empty = "░".repeat(num_empty);
+    match direction {
+        BarDirection::Ltr => format!("{}{}", filled, empty),
+        BarDirection::Rtl => format!("{}{}", empty, filled),
+    }
+}
+
+/// Formats a language share with `precision` decimals. Shares too small to show at that
+/// precision read as `<0.01%` (and so on) rather than a misleading `0.00%`.
+pub fn format_percentage(percentage: f64, precision: usize) -> String {
+    let smallest = 10f64.powi(-(precision as i32));
+    if percentage > 0.0 && percentage < smallest / 2.0 {
+        return format!("<{:.*}%", precision, smallest);
+    }
+    format!("{:.*}%", precision, percentage)
+}
+
+pub fn format_lang_name(lang: &str) -> String {
+    match lang {
+        "Visual Basic .NET" => "VB.NET".to_string(),
+        "Jupyter Notebook" => "Jupyter".to_string(),
+        _ => lang.to_string(),
+    }
+}
+
+pub fn bar_full_scale(scale: BarScale, languages: &[(String, f64)]) -> f64 {
+    match scale {
+        BarScale::Absolute => 100.0,
+        BarScale::Relative => languages.iter().map(|(_, pct)| *pct).fold(0.0, f64::max),
+    }
+}
+
+/// One language's share of the stacked bar.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct StackedSegment {
+    pub name: String,
+    pub color: String,
+    /// In cells.
+    pub width: usize,
+}
+
+/// Splits a bar of `length` cells between the languages by share, with largest-remainder
+/// rounding so the widths always add up to exactly `length`: every language gets the floor of
+/// its exact width, and the cells left over go to the largest fractional parts.
+pub fn stacked_bar(languages: &[(String, f64)], length: usize) -> Vec<StackedSegment> {
+    let total: f64 = languages.iter().map(|(_, pct)| pct.max(0.0)).sum();
+    if total <= 0.0 {
+        return Vec::new();
+    }
+    let exact: Vec<f64> = languages.iter().map(|(_, pct)| pct.max(0.0) / total * length as f64).collect();
+    let mut widths: Vec<usize> = exact.iter().map(|width| width.floor() as usize).collect();
+
+    let mut by_remainder: Vec<usize> = (0..exact.len()).collect();
+    by_remainder.sort_by(|&a, &b| (exact[b] - exact[b].floor()).total_cmp(&(exact[a] - exact[a].floor())));
+    let leftover = length.saturating_sub(widths.iter().sum());
+    for &i in by_remainder.iter().take(leftover) {
+        widths[i] += 1;
+    }
+
+    languages
+        .iter()
+        .zip(widths)
+        .map(|((lang, _), width)| StackedSegment {
+            name: format_lang_name(lang),
+            color: language_color(lang).unwrap_or(FALLBACK_COLOR).to_string(),
+            width,
+        })
+        .collect()
+}
+
+/// Describes the commit count the active mode headlines, so a template can label it with
+/// `{{ commits_source_label }}` instead of hard-coding "this year". When GitHub reported
+/// restricted contributions, says whether `--count-restricted` added them in.
+pub fn commits_source_label(stats: &Stats, config: &Config) -> String {
+    let window = config.describe_window(stats.generated_at);
+    let restricted = match &stats.all_time {
+        Some(all_time) => all_time.restricted_commits,
+        None => stats.restricted_commits,
+    };
+    let note = match (restricted, stats.restricted_included) {
+        (0, _) => "",
+        (_, true) => " (including private)",
+        (_, false) => " (excluding restricted)",
+    };
+    match (&stats.all_time, config.commit_mode) {
+        (Some(_), _) => format!("all-time commits{}", note),
+        (None, CommitMode::Contributions) => format!("commits {}{}", window, note),
+        (None, CommitMode::DefaultBranch) => format!("default-branch commits {}", window),
+    }
+}
+
+/// One of the headline counts in `stats`, for templates that loop over them.
+#[derive(Serialize)]
+pub struct TemplateStat {
+    /// The context variable holding the same value.
+    pub key: &'static str,
+    pub label: String,
+    /// Abbreviated, like the variable.
+    pub value: String,
+    pub raw: u64,
+    /// With `--as-of`, whether this is today's count rather than that day's.
+    pub current: bool,
+}
+
+#[derive(Serialize)]
+pub struct TemplateLanguage {
+    pub name: String,
+    pub bar: String,
+    pub percentage: f64,
+    pub percentage_str: String,
+    /// Since the last `--history-file` entry; null on the first run and where the history
+    /// can't tell.
+    pub trend: Option<LanguageTrend>,
+}
+
+/// A language in the plain `languages_ranked` list: no padding, no bar.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RankedLanguage {
+    /// From 1.
+    pub rank: usize,
+    pub name: String,
+    pub color: String,
+    pub percentage: f64,
+    pub percentage_str: String,
+}
+
+/// Lists the languages in chart order, for templates that draw their own list or chart.
+pub fn ranked_languages(languages: &[(String, f64)], config: &Config) -> Vec<RankedLanguage> {
+    languages
+        .iter()
+        .enumerate()
+        .map(|(i, (lang, percentage))| RankedLanguage {
+            rank: i + 1,
+            name: format_lang_name(lang),
+            color: language_color(lang).unwrap_or(FALLBACK_COLOR).to_string(),
+            percentage: *percentage,
+            percentage_str: format_percentage(*percentage, config.lang_precision.into()),
+        })
+        .collect()
+}
+
+/// Every variable `build_context` can insert, apart from the `has_<category>` flags. Kept
+/// for `--template-lint`; a test checks it against the real context.
+pub const CONTEXT_VARIABLES: &[&str] = &[
+    "username",
+    "stats",
+    "total_stars",
+    "star_distribution",
+    "median_stars",
+    "repos_over_10_stars",
+    "repos_over_100_stars",
+    "repos_over_1000_stars",
+    "total_prs",
+    "total_issues",
+    "prs_this_year",
+    "issues_this_year",
+    "contributed_repos",
+    "top_repos",
+    "pinned_repos",
+    "organizations",
+    "stars_given",
+    "commits_source_label",
+    "window",
+    "languages",
+    "languages_empty_reason",
+    "contributed_languages",
+    "estimated_lines",
+    "stacked_bar",
+    "languages_stacked",
+    "languages_ranked",
+    "degraded",
+    "fallback_used",
+    "extra",
+    "weekday_distribution",
+    "contribution_grid",
+    "total_commits_this_year",
+    "contributed_to",
+    "repos_committed_this_year",
+    "top_language",
+    "top_language_percentage",
+    "top_language_color",
+    "total_commits_all_time",
+    "productive_years",
+    "commits_vs_average",
+    "influence_score",
+    "account_created",
+    "account_age_years",
+    "account_age_days",
+    "total_disk_usage",
+    "external_prs",
+    "external_issues",
+    "compare",
+    "years_active",
+    "language_timeline",
+    "stars_delta",
+    "commits_delta",
+    "commits_since_last_run",
+    "stars_history",
+    "commits_history",
+    "last_updated",
+    "generated_at",
+    "data_age",
+    "as_of_date",
+];
+
+/// The context variables each `--only` category owns.
+fn category_variables(category: StatCategory) -> &'static [&'static str] {
+    match category {
+        StatCategory::Stars => &[
+            "total_stars",
+            "star_distribution",
+            "median_stars",
+            "repos_over_10_stars",
+            "repos_over_100_stars",
+            "repos_over_1000_stars",
+            "top_repos",
+            "influence_score",
+            "total_disk_usage",
+            "stars_delta",
+            "stars_history",
+        ],
+        StatCategory::Commits => &[
+            "total_commits_this_year",
+            "commits_source_label",
+            "total_commits_all_time",
+            "productive_years",
+            "commits_vs_average",
+            "years_active",
+            "weekday_distribution",
+            "contribution_grid",
+            "commits_delta",
+            "commits_since_last_run",
+            "commits_history",
+        ],
+        StatCategory::Prs => &["total_prs", "prs_this_year", "external_prs"],
+        StatCategory::Issues => &["total_issues", "issues_this_year", "external_issues"],
+        StatCategory::Contributions => &["contributed_to", "repos_committed_this_year", "contributed_repos"],
+        StatCategory::Starred => &["stars_given"],
+        StatCategory::Languages => &[
+            "languages",
+            "languages_empty_reason",
+            "contributed_languages",
+            "stacked_bar",
+            "languages_stacked",
+            "languages_ranked",
+            "top_language",
+            "top_language_percentage",
+            "top_language_color",
+            "language_timeline",
+            "estimated_lines",
+        ],
+    }
+}
+
+/// Shapes a language breakdown for the templates, each bar scaled against its own chart, with
+/// the `trends` of the chart the history keeps.
+fn template_languages(
+    languages: &[(String, f64)],
+    trends: Option<&BTreeMap<String, LanguageTrend>>,
+    config: &Config,
+) -> Vec<TemplateLanguage> {
+    let full_scale = bar_full_scale(config.bar_scale, languages);
+    languages
+        .iter()
+        .map(|(lang, percentage)| TemplateLanguage {
+            name: format!("{:<15}", format_lang_name(lang)),
+            bar: render_progress_bar(
+                *percentage,
+                full_scale,
+                config.bar_direction,
+                config
+                    .ansi_color
+                    .then(|| nearest_ansi256(language_color(lang).unwrap_or(FALLBACK_COLOR)))
+                    .flatten(),
+            ),
+            percentage: *percentage,
+            percentage_str: format_percentage(*percentage, config.lang_precision.into()),
+            trend: trends.and_then(|trends| trends.get(lang)).copied(),
+        })
+        .collect()
+}
+
+/// The context variables that only the `--skip-field` part of the query provides.
+fn skipped_field_variables(field: SkipField) -> &'static [&'static str] {
+    match field {
+        SkipField::ContributionCalendar => &["weekday_distribution", "contribution_grid"],
+        SkipField::Repositories => &[
+            "total_stars",
+            "star_distribution",
+            "median_stars",
+            "repos_over_10_stars",
+            "repos_over_100_stars",
+            "repos_over_1000_stars",
+            "top_repos",
+            "influence_score",
+            "total_disk_usage",
+        ],
+        SkipField::ContributedRepos => &["contributed_to", "contributed_repos"],
+        SkipField::PinnedItems => &["pinned_repos"],
+        SkipField::Organizations => &["organizations"],
+    }
+}
+
+/// The variables every template sees.
+///
+/// Always present:
+///
+/// - `username`, and the counts `total_stars`, `total_prs`, `total_issues`,
+///   `prs_this_year`, `issues_this_year` and `stars_given`
+/// - `stats`: the stars, commits (all-time ones with `--all-time`), PRs, issues and repos
+///   contributed to, for `{% for stat in stats %}`. Each has a `key` (the variable it
+///   mirrors), `label`, `value`, `raw` (unabbreviated) and `current` (a count from today
+///   despite `--as-of`). Counts that are unavailable or left out aren't listed.
+/// - `contributed_repos`, `organizations` (`login` and `avatar_url` each), and
+///   `top_repos` and `pinned_repos`: `name`, `description`, `stars`, and `language` and
+///   `language_color` for a colored dot, both null without a primary language
+/// - `commits_source_label`, `window`, `weekday_distribution`, and `contribution_grid`:
+///   the calendar in `·░▒▓█`, 7 lines of days by a column per week, for a code block;
+///   empty over REST
+/// - `degraded`, `fallback_used` (the stats are the `--fallback-user`'s) and `extra`
+/// - `total_disk_usage` (bytes, for `| human_bytes`), `last_updated`, `generated_at`
+///   (RFC 3339), and `data_age`: how long before rendering the stats were gathered, like
+///   `3 hours ago` or `just now`; in `serve`, the age of the cached stats
+///
+/// The languages, in shapes drawn from the same breakdown:
+///
+/// - `languages`: a padded `name`, `bar`, `percentage` and `percentage_str` each, and a
+///   `trend` against the last `--history-file` entry: `up`, `down`, `flat` (within half a
+///   point) or `new`. The trend is null on the first run, and below the top three for a
+///   language the last entry didn't keep.
+/// - `languages_stacked`, also as `stacked_bar`: segments of `--stacked-bar-length`
+///   cells, with `name`, `color` and `width`
+/// - `languages_ranked`: `rank`, `name`, `color`, `percentage` and `percentage_str`, with
+///   nothing drawn
+/// - `contributed_languages`: like `languages` without trends; empty without
+///   `--contributed-languages`
+///
+/// Present but null when unavailable, so `| or_zero` or `| or_dash` can stand in:
+///
+/// - `languages_empty_reason`: `no_repos`, `all_filtered`, `unavailable`, `no_code` or
+///   `below_min_bytes`; null while there are languages
+/// - `top_language`, `top_language_percentage` and `top_language_color`, with no languages
+/// - `estimated_lines`, without `--estimate-lines`; a whole number, for `| group`
+/// - `total_commits_this_year`, `contributed_to` and `repos_committed_this_year`, over
+///   REST
+/// - `total_commits_all_time` and `productive_years`, without `--all-time`, and
+///   `commits_vs_average`: this year's commits against the yearly average before it, as a
+///   whole signed percentage like `23` or `-8`; also null in the account's first year
+/// - `influence_score`, `star_distribution` and its `median_stars`, `repos_over_10_stars`,
+///   `repos_over_100_stars` and `repos_over_1000_stars` (at least that many stars) on
+///   their own
+/// - `account_created` (`2015-03-10`, the date at `--utc-offset`), `account_age_years`
+///   (one decimal) and `account_age_days`, over REST
+/// - `external_prs` and `external_issues`, without `--external-contributions`
+/// - `as_of_date`: the `--as-of` day. The commits, PRs and issues in the window, the
+///   all-time commits and the account age are as of then; everything else is current.
+///
+/// Left out when unavailable:
+///
+/// - `compare`, `years_active` and `language_timeline`
+/// - `commits_since_last_run`, without `--since-last-run` or on the first run
+/// - `stars_delta`, `commits_delta`, `stars_history` and `commits_history`, with no
+///   `--history-file` entries yet; `commits_delta` also when either run lacks commits
+///
+/// With `--only`, the variables of the categories not listed are left out altogether, and
+/// `has_<category>` (`has_stars`, `has_languages`, ...) says which ones are there. Each
+/// `--skip-field` leaves out the variables built from that part of the query.
+pub fn build_context(stats: &Stats, config: &Config) -> Context {
+    let mut context = Context::new();
+
+    context.insert("username", &stats.username);
+    context.insert("total_stars", &abbreviate_number(stats.total_stars, config));
+    context.insert("total_prs", &abbreviate_number(stats.total_prs, config));
+    context.insert("total_issues", &abbreviate_number(stats.total_issues, config));
+    context.insert("prs_this_year", &abbreviate_number(stats.prs_this_year, config));
+    context.insert("issues_this_year", &abbreviate_number(stats.issues_this_year, config));
+    context.insert("contributed_repos", &stats.contributed_repos);
+    context.insert("top_repos", &stats.top_repos);
+    context.insert("pinned_repos", &stats.pinned_repos);
+    context.insert("organizations", &stats.organizations);
+    context.insert("stars_given", &abbreviate_number(stats.stars_given, config));
+    context.insert("commits_source_label", &commits_source_label(stats, config));
+    context.insert("window", &config.describe_window(stats.generated_at));
+    let trends = stats.trend.as_ref().map(|trend| &trend.languages);
+    context.insert("languages", &template_languages(&stats.languages, trends, config));
+    context.insert("languages_empty_reason", &stats.languages_empty_reason);
+    context.insert("contributed_languages", &template_languages(&stats.contributed_languages, None, config));
+    let stacked = stacked_bar(&stats.languages, config.stacked_bar_length);
+    context.insert("languages_stacked", &stacked);
+    context.insert("stacked_bar", &stacked);
+    context.insert("languages_ranked", &ranked_languages(&stats.languages, config));
+    context.insert("degraded", &stats.degraded);
+    context.insert("fallback_used", &stats.fallback_used);
+    context.insert("extra", &stats.extra);
+    context.insert("weekday_distribution", &stats.weekday_distribution);
+    context.insert("contribution_grid", &stats.contribution_grid);
+    context.insert("total_disk_usage", &stats.total_disk_usage);
+
+    let top_language = stats.languages.first();
+    let all_time = stats.all_time.as_ref();
+    context.insert("total_commits_this_year", &stats.total_commits_this_year.map(|n| abbreviate_number(n, config)));
+    context.insert("contributed_to", &stats.contributed_to.map(|n| abbreviate_number(n, config)));
+    context.insert("repos_committed_this_year", &stats.repos_committed_this_year.map(|n| abbreviate_number(n, config)));
+    context.insert("top_language", &top_language.map(|(lang, _)| format_lang_name(lang)));
+    context.insert("top_language_percentage", &top_language.map(|(_, percentage)| percentage));
+    context.insert("estimated_lines", &stats.estimated_lines);
+    context.insert(
+        "top_language_color",
+        &top_language.map(|(lang, _)| language_color(lang).unwrap_or(FALLBACK_COLOR)),
+    );
+    context.insert("total_commits_all_time", &all_time.map(|all_time| abbreviate_number(all_time.total_commits, config)));
+    context.insert("productive_years", &all_time.map(|all_time| all_time.years_active.len()));
+    context.insert(
+        "commits_vs_average",
+        &all_time.and_then(|all_time| all_time.commits_vs_average).map(|percent| percent.round() as i64),
+    );
+    context.insert("influence_score", &stats.influence_score.map(|score| abbreviate_number(score.round() as u64, config)));
+    let distribution = stats.star_distribution.as_ref();
+    context.insert("star_distribution", &distribution);
+    context.insert("median_stars", &distribution.map(|distribution| number_value(distribution.median_stars)));
+    context.insert("repos_over_10_stars", &distribution.map(|distribution| distribution.repos_over_10_stars));
+    context.insert("repos_over_100_stars", &distribution.map(|distribution| distribution.repos_over_100_stars));
+    context.insert("repos_over_1000_stars", &distribution.map(|distribution| distribution.repos_over_1000_stars));
+    let account_age = stats.account_created.map(|created| config.counts_until(stats.generated_at) - created);
+    context.insert(
+        "account_created",
+        &stats.account_created.map(|created| created.with_timezone(&config.utc_offset).format("%Y-%m-%d").to_string()),
+    );
+    context.insert(
+        "account_age_years",
+        &account_age.map(|age| (age.num_seconds() as f64 / (365.25 * 86_400.0) * 10.0).round() / 10.0),
+    );
+    context.insert("account_age_days", &account_age.map(|age| age.num_days()));
+    context.insert("external_prs", &stats.external_prs.map(|n| abbreviate_number(n, config)));
+    context.insert("external_issues", &stats.external_issues.map(|n| abbreviate_number(n, config)));
+
+    if let Some(compare) = &stats.compare {
+        context.insert("compare", compare);
+    }
+    if let Some(all_time) = all_time {
+        context.insert("years_active", &all_time.years_active);
+        context.insert("language_timeline", &all_time.language_timeline);
+    }
+    if let Some(commits) = stats.commits_since_last_run {
+        context.insert("commits_since_last_run", &abbreviate_number(commits, config));
+    }
+    if let Some(trend) = &stats.trend {
+        context.insert("stars_delta", &trend.stars_delta);
+        if let Some(commits_delta) = trend.commits_delta {
+            context.insert("commits_delta", &commits_delta);
+        }
+        context.insert("stars_history", &trend.stars_history);
+        context.insert("commits_history", &trend.commits_history);
+    }
+    context.insert("last_updated", &format!("Last updated {} UTC", stats.generated_at.format("%Y-%m-%d %H:%M:%S")));
+    context.insert("generated_at", &stats.generated_at.to_rfc3339());
+    context.insert("as_of_date", &config.as_of.map(|date| date.to_string()));
+    let rendered_at = config.now.unwrap_or_else(Utc::now);
+    context.insert("data_age", &human_age(rendered_at - stats.generated_at));
+
+    for category in StatCategory::value_variants() {
+        let wanted = config.wants(*category);
+        context.insert(category.flag(), &wanted);
+        if !wanted {
+            for variable in category_variables(*category) {
+                context.remove(variable);
+            }
+        }
+    }
+    for field in &config.skip_fields {
+        for variable in skipped_field_variables(*field) {
+            context.remove(variable);
+        }
+    }
+    let listed = template_stats(stats, config)
+        .into_iter()
+        .filter(|stat| context.contains_key(stat.key))
+        .collect::<Vec<_>>();
+    context.insert("stats", &listed);
+    context
+}
+
+/// The `stats` GitHub can only give as they are now, whatever the `--as-of`.
+const AS_OF_CURRENT: &[&str] = &["total_stars", "total_prs", "total_issues", "contributed_to"];
+
+/// The entries of `stats`, before dropping the ones whose variable was left out.
+fn template_stats(stats: &Stats, config: &Config) -> Vec<TemplateStat> {
+    let commits = match &stats.all_time {
+        Some(all_time) => Some(("total_commits_all_time", all_time.total_commits)),
+        None => stats.total_commits_this_year.map(|commits| ("total_commits_this_year", commits)),
+    };
+    let commits = commits.map(|(key, raw)| (key, capitalize(&commits_source_label(stats, config)), raw));
+    [
+        Some(("total_stars", "Stars".to_string(), stats.total_stars)),
+        commits,
+        Some(("total_prs", "Pull requests".to_string(), stats.total_prs)),
+        Some(("total_issues", "Issues".to_string(), stats.total_issues)),
+        stats.contributed_to.map(|contributed_to| ("contributed_to", "Repos contributed to".to_string(), contributed_to)),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|(key, label, raw)| TemplateStat {
+        key,
+        label,
+        value: abbreviate_number(raw, config),
+        raw,
+        current: config.as_of.is_some() && AS_OF_CURRENT.contains(&key),
+    })
+    .collect()
+}
+
+/// `age` rounded down to its largest whole unit, as in "3 hours ago".
+pub fn human_age(age: chrono::TimeDelta) -> String {
+    let (count, unit) = match age.num_seconds() {
+        ..60 => return "just now".to_string(),
+        seconds @ ..3_600 => (seconds / 60, "minute"),
+        seconds @ ..86_400 => (seconds / 3_600, "hour"),
+        seconds => (seconds / 86_400, "day"),
+    };
+    format!("{} {}{} ago", count, unit, if count == 1 { "" } else { "s" })
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+pub fn render_readme(tera: &Tera, stats: &Stats, config: &Config) -> Result<String, tera::Error> {
+    tera.render(&template_name(config, "README.md.tera"), &build_context(stats, config))
+}
+
+pub fn render_svg(tera: &Tera, stats: &Stats, config: &Config) -> Result<String, tera::Error> {
+    tera.render(&template_name(config, "stats.svg.tera"), &build_context(stats, config))
+}
+
+/// The `--template-file` or `--template-string`, for the formats that bundle no template;
+/// `Config` makes sure `--format html` has one.
+pub fn render_custom(tera: &Tera, stats: &Stats, config: &Config) -> Result<String, tera::Error> {
+    tera.render(&template_name(config, TEMPLATE_STRING_NAME), &build_context(stats, config))
+}
+
+/// The `--template-string` or `--template-file`, if given, or else the `bundled` template.
+fn template_name(config: &Config, bundled: &str) -> String {
+    if config.template_string.is_some() {
+        return TEMPLATE_STRING_NAME.to_string();
+    }
+    config.template_file_name().unwrap_or_else(|| bundled.to_string())
+}
+
+/// Renders the stats in the configured output format.
+pub fn render_output(tera: &Tera, stats: &Stats, config: &Config) -> Result<Vec<u8>, Error> {
+    Ok(match config.format {
+        Format::Markdown => render_readme(tera, stats, config)?.into_bytes(),
+        Format::Svg => render_svg(tera, stats, config)?.into_bytes(),
+        Format::Png => rasterize(&render_svg(tera, stats, config)?, config.png_scale)?,
+        Format::Prometheus => render_prometheus(stats, config).into_bytes(),
+        Format::Openmetrics => {
+            let mut entries = config.history_file.as_deref().map(read_history).unwrap_or_default();
+            entries.push(HistoryEntry::from_stats(stats));
+            render_openmetrics(&stats.username, &entries, config).into_bytes()
+        }
+        Format::Oneline => render_oneline(stats, &config.oneline_format, config).map_err(Error::Config)?.into_bytes(),
+        Format::Shields => render_shields(stats, config.badge, config).into_bytes(),
+        Format::Html => render_custom(tera, stats, config)?.into_bytes(),
+        Format::Json if config.template_file.is_some() => render_custom(tera, stats, config)?.into_bytes(),
+        Format::Json => render_json(stats, config)?.into_bytes(),
+    })
+}
+
+/// The stats as JSON, pretty or compact per `Config::json_is_pretty`, ending in a newline.
+pub fn render_json(stats: &Stats, config: &Config) -> Result<String, Error> {
+    let json = if config.json_is_pretty() { serde_json::to_string_pretty(stats) } else { serde_json::to_string(stats) };
+    Ok(json.map_err(Error::Encode)? + "\n")
+}
+
+/// `render_output` held to `--max-output-bytes` once encoded, dropping languages from the
+/// bottom of the chart under `--fit-output` (see `drop_smallest_language`).
+pub fn render_within_budget(tera: &Tera, stats: &Stats, config: &Config) -> Result<Vec<u8>, Error> {
+    let rendered = render_output(tera, stats, config)?;
+    let Some(budget) = config.max_output_bytes else {
+        return Ok(rendered);
+    };
+    let mut size = encode_output(rendered.clone(), config).len();
+    if size <= budget {
+        return Ok(rendered);
+    }
+    if config.fit_output {
+        let mut fitted = stats.clone();
+        while drop_smallest_language(&mut fitted.languages, config) {
+            let rendered = render_output(tera, &fitted, config)?;
+            size = encode_output(rendered.clone(), config).len();
+            if size <= budget {
+                let dropped = stats.languages.len() - fitted.languages.len();
+                eprintln!("warning: left out the smallest {} language(s) to fit --max-output-bytes {}", dropped, budget);
+                return Ok(rendered);
+            }
+        }
+    }
+    Err(Error::Config(format!("the output is {} bytes, over --max-output-bytes {}", size, budget)))
+}
+
+/// Takes the smallest language off the chart. Under `--lang-other` its share moves into
+/// `Other`, which stays last, so the chart still adds up; `Other` itself goes once it's all
+/// that's left. `false` when the chart was already empty.
+fn drop_smallest_language(languages: &mut Vec<(String, f64)>, config: &Config) -> bool {
+    let other = languages.len().checked_sub(1).filter(|&last| config.lang_other && languages[last].0 == "Other");
+    match other {
+        Some(other) if other > 0 => {
+            let (_, share) = languages.remove(other - 1);
+            languages[other - 1].1 += share;
+            true
+        }
+        _ => languages.pop().is_some(),
+    }
+}
+
+/// Applies `--line-endings` and `--bom` to text output; PNG bytes pass through untouched.
+pub fn encode_output(rendered: Vec<u8>, config: &Config) -> Vec<u8> {
+    if !config.format.is_text() {
+        return rendered;
+    }
+    let text = String::from_utf8_lossy(&rendered).replace("\r\n", "\n");
+    let text = match config.line_endings {
+        LineEndings::Lf => text,
+        LineEndings::Crlf => text.replace('\n', "\r\n"),
+    };
+    let bom: &[u8] = if config.bom { "\u{feff}".as_bytes() } else { b"" };
+    [bom, text.as_bytes()].concat()
+}
@@ -0,0 +1,98 @@
+use crate::config::{Badge, Config, ServeArgs};
+use crate::error::Error;
+use crate::http::Transport;
+use crate::metrics::render_prometheus;
+use crate::render::render_svg;
+use crate::shields::render_shields;
+use crate::stats::{Stats, gather_stats};
+use chrono::prelude::*;
+use clap::ValueEnum;
+use std::time::{Duration, Instant};
+use tera::Tera;
+use tiny_http::{Header, Response, Server};
+
+/// Holds the last gathered stats until they are older than the TTL, so page loads don't each
+/// cost a round of GitHub API calls.
+struct StatsCache {
+    ttl: Duration,
+    entry: Option<(Instant, Stats)>,
+}
+
+impl StatsCache {
+    /// The cached stats, refreshed first if stale, and how long ago they were gathered.
+    fn get_or_refresh(
+        &mut self,
+        refresh: impl FnOnce() -> Result<Stats, Error>,
+    ) -> Result<(Duration, &Stats), Error> {
+        let fresh = matches!(&self.entry, Some((fetched_at, _)) if fetched_at.elapsed() < self.ttl);
+        if !fresh {
+            self.entry = Some((Instant::now(), refresh()?));
+        }
+        let (fetched_at, stats) = self.entry.as_ref().unwrap();
+        Ok((fetched_at.elapsed(), stats))
+    }
+}
+
+pub fn serve(
+    http: &dyn Transport,
+    username: &str,
+    config: &Config,
+    tera: &Tera,
+    args: &ServeArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let server = Server::http(&args.bind).map_err(|e| format!("failed to bind {}: {}", args.bind, e))?;
+    let mut cache = StatsCache {
+        ttl: Duration::from_secs(args.cache_ttl),
+        entry: None,
+    };
+    eprintln!("serving stats for {} on http://{}", username, args.bind);
+
+    for request in server.incoming_requests() {
+        let path = request.url().split('?').next().unwrap_or("").to_string();
+        let badge = badge_for_path(&path);
+        let known = badge.is_some() || matches!(path.as_str(), "/stats.svg" | "/stats.json" | "/metrics");
+        let response = if !known {
+            Response::from_string("not found").with_status_code(404)
+        } else {
+            let stats = cache.get_or_refresh(|| {
+                gather_stats(http, username, config, config.now.unwrap_or_else(Utc::now))
+            });
+            let body = stats.and_then(|(age, stats)| {
+                let age = age.as_secs().to_string();
+                if let Some(badge) = badge {
+                    return Ok((render_shields(stats, badge, config), "application/json", age));
+                }
+                match path.as_str() {
+                    "/stats.svg" => Ok((render_svg(tera, stats, config)?, "image/svg+xml", age)),
+                    "/metrics" => Ok((render_prometheus(stats, config), "text/plain; version=0.0.4", age)),
+                    _ => Ok((serde_json::to_string_pretty(stats).map_err(Error::Encode)?, "application/json", age)),
+                }
+            });
+            match body {
+                // `Age` tells caches (and curious users) how stale the served stats are.
+                Ok((body, content_type, age)) => Response::from_string(body)
+                    .with_header(header("Content-Type", content_type))
+                    .with_header(header("Age", &age))
+                    .with_header(header("Cache-Control", &format!("max-age={}", args.cache_ttl))),
+                Err(e) => {
+                    eprintln!("failed to serve {}: {}", path, e);
+                    Response::from_string(format!("failed to gather stats: {}", e)).with_status_code(502)
+                }
+            }
+        };
+        if let Err(e) = request.respond(response) {
+            eprintln!("failed to send response: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// `/badge/<stat>.json`, e.g. `/badge/top-language.json`.
+fn badge_for_path(path: &str) -> Option<Badge> {
+    let stat = path.strip_prefix("/badge/")?.strip_suffix(".json")?;
+    Badge::from_str(stat, false).ok()
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name, value).expect("static header is valid")
+}
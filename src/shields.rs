@@ -0,0 +1,37 @@
+use crate::colors::{FALLBACK_COLOR, language_color};
+use crate::config::{Badge, Config};
+use crate::render::{abbreviate_number, format_lang_name};
+use crate::stats::Stats;
+use serde_json::json;
+
+/// The card's accent color, for badges that don't carry a color of their own.
+const BADGE_COLOR: &str = "e56b9f";
+const UNAVAILABLE_COLOR: &str = "lightgrey";
+
+/// A shields.io endpoint response (https://shields.io/badges/endpoint-badge) for one stat.
+pub fn render_shields(stats: &Stats, badge: Badge, config: &Config) -> String {
+    let (label, message, color) = match badge {
+        Badge::Stars => ("stars", Some(abbreviate_number(stats.total_stars, config)), BADGE_COLOR),
+        Badge::Commits => ("commits", stats.total_commits_this_year.map(|n| abbreviate_number(n, config)), BADGE_COLOR),
+        Badge::Prs => ("pull requests", Some(abbreviate_number(stats.total_prs, config)), BADGE_COLOR),
+        Badge::TopLanguage => match stats.languages.first() {
+            Some((lang, _)) => (
+                "top language",
+                Some(format_lang_name(lang)),
+                language_color(lang).unwrap_or(FALLBACK_COLOR).trim_start_matches('#'),
+            ),
+            None => ("top language", None, UNAVAILABLE_COLOR),
+        },
+    };
+    let (message, color) = match message {
+        Some(message) => (message, color),
+        None => ("n/a".to_string(), UNAVAILABLE_COLOR),
+    };
+    let endpoint = json!({
+        "schemaVersion": 1,
+        "label": label,
+        "message": message,
+        "color": color,
+    });
+    format!("{}\n", endpoint)
+}
@@ -0,0 +1,790 @@
+use crate::config::{AuthMode, CommitMode, Config, CountRestricted, LangFrom, LangMetric, StatCategory};
+use crate::error::Error;
+use crate::github::{ContributionCalendar, User, check_token, query_commit_languages, query_created_at, query_default_branch_commits, query_external_contributions, query_commits_between, query_contributed_languages, query_rest_stats, query_user_stats, query_yearly_commits};
+use crate::history::{Trend, read_history, trend};
+use crate::http::Transport;
+use crate::languages::{
+    LanguageStats, NoLanguages, RepoFilter, RepoLanguages, RepoPrimaryLanguage, RepoSelection, calculate_language_stats, check_languages_sum,
+    estimate_lines, fetch_repo_languages, language_shares, language_timeline, no_primary_languages, repo_count_shares,
+};
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Everything the templates know about a user, gathered in one pass over the API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stats {
+    pub username: String,
+    pub generated_at: DateTime<Utc>,
+    pub total_stars: u64,
+    /// `None` when GraphQL was unavailable.
+    #[serde(default)]
+    pub star_distribution: Option<StarDistribution>,
+    /// `None` when GraphQL was unavailable; REST has no commit contribution counts.
+    pub total_commits_this_year: Option<u64>,
+    /// Whether the commit counts include restricted contributions, per `--count-restricted`.
+    #[serde(default)]
+    pub restricted_included: bool,
+    /// The restricted contributions GitHub reported for the window, counted in
+    /// `total_commits_this_year` only if `restricted_included`. 0 outside the contributions
+    /// `--commit-mode`.
+    #[serde(default)]
+    pub restricted_commits: u64,
+    pub total_prs: u64,
+    pub total_issues: u64,
+    pub prs_this_year: u64,
+    pub issues_this_year: u64,
+    /// `None` when GraphQL was unavailable.
+    pub contributed_to: Option<u64>,
+    /// Distinct repos committed to in the window, zero for a quiet one. `None` when GraphQL
+    /// was unavailable.
+    #[serde(default)]
+    pub repos_committed_this_year: Option<u64>,
+    pub contributed_repos: Vec<RepoSummary>,
+    /// Your `--top-repos` most-starred owned repos, most-starred first. Empty when GraphQL
+    /// was down.
+    #[serde(default)]
+    pub top_repos: Vec<TopRepo>,
+    /// Empty when nothing is pinned, or GraphQL was down.
+    #[serde(default)]
+    pub pinned_repos: Vec<PinnedRepo>,
+    /// Up to `--organizations` of them. Without `read:org`, only public memberships; empty
+    /// when there are none, or GraphQL was down.
+    #[serde(default)]
+    pub organizations: Vec<OrganizationSummary>,
+    /// When the account was created. `None` when GraphQL was down.
+    #[serde(default)]
+    pub account_created: Option<DateTime<Utc>>,
+    pub stars_given: u64,
+    pub languages: Vec<(String, f64)>,
+    /// Why `languages` is empty, when it is despite being asked for.
+    #[serde(default)]
+    pub languages_empty_reason: Option<NoLanguages>,
+    /// Lines of code in the charted repos, roughly. Only with `--estimate-lines`.
+    #[serde(default)]
+    pub estimated_lines: Option<u64>,
+    /// The same breakdown over the repos you contributed to. Only with
+    /// `--contributed-languages`.
+    #[serde(default)]
+    pub contributed_languages: Vec<(String, f64)>,
+    pub all_time: Option<AllTimeStats>,
+    /// GraphQL was down and the counts above came from the REST fallback.
+    pub degraded: bool,
+    /// These are the `--fallback-user`'s stats, the configured user's being empty.
+    #[serde(default)]
+    pub fallback_used: bool,
+    /// The `--compare-user`'s totals, if one was given and could be fetched.
+    pub compare: Option<CompareStats>,
+    /// The `--extra-field`s GitHub answered, keyed by field name.
+    pub extra: serde_json::Map<String, serde_json::Value>,
+    /// See `influence_score`. `None` when GraphQL was down.
+    pub influence_score: Option<f64>,
+    /// Contributions in the window per weekday, Monday first. Empty when GraphQL was down.
+    pub weekday_distribution: Vec<WeekdayCount>,
+    /// The contribution calendar as text; see `contribution_grid`. Empty when GraphQL was
+    /// down.
+    #[serde(default)]
+    pub contribution_grid: String,
+    /// Opened in repos you don't own; only counted with `--external-contributions`.
+    #[serde(default)]
+    pub external_prs: Option<u64>,
+    #[serde(default)]
+    pub external_issues: Option<u64>,
+    /// Summed across owned repos, in bytes.
+    #[serde(default)]
+    pub total_disk_usage: u64,
+    /// Commit contributions since the last `--history-file` entry. Only with
+    /// `--since-last-run`, and not on the first run.
+    #[serde(default)]
+    pub commits_since_last_run: Option<u64>,
+    /// The change since the last `--history-file` entry. `None` without one.
+    pub trend: Option<Trend>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeekdayCount {
+    /// `Mon` through `Sun`.
+    pub weekday: String,
+    pub contributions: u64,
+}
+
+/// Sums the contribution calendar's days by weekday. The window bounds carry `--utc-offset`,
+/// so GitHub can date the days at your midnight rather than UTC's.
+fn weekday_distribution(calendar: Option<&ContributionCalendar>) -> Vec<WeekdayCount> {
+    let mut counts = [0u64; 7];
+    for day in calendar.iter().flat_map(|calendar| &calendar.weeks).flat_map(|week| &week.contribution_days) {
+        counts[day.date.weekday().num_days_from_monday() as usize] += day.contribution_count;
+    }
+    let weekdays = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun];
+    weekdays
+        .iter()
+        .zip(counts)
+        .map(|(weekday, contributions)| WeekdayCount {
+            weekday: weekday.to_string(),
+            contributions,
+        })
+        .collect()
+}
+
+/// The shading of a day with no contributions, then of each quarter of the busiest day's.
+const GRID_LEVELS: [char; 5] = ['·', '░', '▒', '▓', '█'];
+
+/// Draws the contribution calendar as 7 rows of days, Sunday first as on GitHub, by one
+/// column per week, each day shaded by its share of the busiest day's count. The window's
+/// partial first and last weeks leave blanks for the days outside it; rows end at their
+/// last day.
+fn contribution_grid(calendar: Option<&ContributionCalendar>) -> String {
+    let weeks = calendar.map_or(&[][..], |calendar| &calendar.weeks[..]);
+    if weeks.is_empty() {
+        return String::new();
+    }
+    let busiest = weeks.iter().flat_map(|week| &week.contribution_days).map(|day| day.contribution_count).max().unwrap_or(0);
+    let mut rows = vec![String::new(); 7];
+    for week in weeks {
+        let mut column = [' '; 7];
+        for day in &week.contribution_days {
+            let level = (day.contribution_count * 4).div_ceil(busiest.max(1)) as usize;
+            column[day.date.weekday().num_days_from_sunday() as usize] = GRID_LEVELS[level.min(4)];
+        }
+        for (row, cell) in rows.iter_mut().zip(column) {
+            row.push(cell);
+        }
+    }
+    rows.iter().map(|row| row.trim_end()).collect::<Vec<_>>().join("\n")
+}
+
+/// Another user's headline totals next to ours; each `_delta` is ours minus theirs.
+/// Languages are left out, since the repo listing only covers the token's own account.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompareStats {
+    pub username: String,
+    pub total_stars: u64,
+    pub total_commits_this_year: u64,
+    pub total_prs: u64,
+    pub total_issues: u64,
+    pub contributed_to: u64,
+    pub stars_given: u64,
+    pub stars_delta: i64,
+    /// `None` when our own commit count is unavailable.
+    pub commits_delta: Option<i64>,
+    pub prs_delta: i64,
+    pub issues_delta: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepoSummary {
+    pub name_with_owner: String,
+    pub stars: u64,
+}
+
+/// How the stars spread over your owned repos.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StarDistribution {
+    /// The middle repo's stars, or the mean of the middle two. 0 without repos.
+    pub median_stars: f64,
+    pub repos_over_10_stars: u64,
+    pub repos_over_100_stars: u64,
+    pub repos_over_1000_stars: u64,
+}
+
+/// One of your most-starred repos.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopRepo {
+    pub name: String,
+    pub description: Option<String>,
+    pub stars: u64,
+    /// Null for a repo without a primary language, like `PinnedRepo`'s.
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub language_color: Option<String>,
+}
+
+/// An organization you're a member of.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrganizationSummary {
+    pub login: String,
+    pub avatar_url: String,
+}
+
+/// A repo pinned to the profile, in pin order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PinnedRepo {
+    pub name: String,
+    pub description: Option<String>,
+    pub stars: u64,
+    pub language: Option<String>,
+    /// The language's Linguist color as GitHub reports it, e.g. `#dea584`.
+    pub language_color: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YearCommits {
+    pub year: i32,
+    pub commits: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YearLanguage {
+    pub year: i32,
+    pub language: String,
+}
+
+/// Lifetime commit activity, only gathered with `--all-time`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllTimeStats {
+    pub total_commits: u64,
+    /// Every year since the account was created, including empty ones.
+    pub years: Vec<YearCommits>,
+    /// The restricted contributions GitHub reported for the counted years, in
+    /// `total_commits` only if `Stats::restricted_included`.
+    #[serde(default)]
+    pub restricted_commits: u64,
+    /// The years that reached `--min-year-commits`.
+    pub years_active: Vec<YearCommits>,
+    /// How far this year's commits so far are above (or below, negative) the average of the
+    /// years before it, in percent. `None` in the account's first year, or when every earlier
+    /// year is empty.
+    #[serde(default)]
+    pub commits_vs_average: Option<f64>,
+    /// Each year's dominant language, approximated from the repos created that year (see
+    /// `languages::language_timeline`). Years without a clear winner are left out.
+    pub language_timeline: Vec<YearLanguage>,
+}
+
+/// Lifetime commit totals from the account's creation year on. `created_at` saves a query
+/// when the main one already fetched it.
+fn gather_all_time(
+    http: &dyn Transport,
+    username: &str,
+    config: &Config,
+    now: DateTime<Utc>,
+    repo_languages: &[RepoLanguages],
+    include_restricted: bool,
+    created_at: Option<DateTime<Utc>>,
+) -> Result<AllTimeStats, Error> {
+    let first_year = match created_at {
+        Some(created_at) => created_at.year(),
+        None => query_created_at(http, username, config)?.year(),
+    };
+    let collections = query_yearly_commits(http, username, first_year..=now.year(), config)?;
+    let years: Vec<YearCommits> = collections
+        .iter()
+        .map(|(year, collection)| YearCommits { year: *year, commits: collection.commits(include_restricted) })
+        .collect();
+
+    let years_active: Vec<YearCommits> = years
+        .iter()
+        .filter(|y| y.commits > 0 && y.commits >= config.min_year_commits)
+        .cloned()
+        .collect();
+    let counted = if config.exclude_inactive_years { &years_active } else { &years };
+    let total_commits = counted.iter().map(|y| y.commits).sum();
+    let restricted_commits = collections
+        .iter()
+        .filter(|(year, _)| counted.iter().any(|y| y.year == *year))
+        .map(|(_, collection)| collection.restricted_contributions_count)
+        .sum();
+    let this_year = years.iter().find(|y| y.year == now.year()).map_or(0, |y| y.commits);
+    let commits_vs_average = commits_vs_average(this_year, counted.iter().filter(|y| y.year < now.year()));
+
+    let language_timeline = language_timeline(repo_languages)
+        .into_iter()
+        .filter(|(year, _)| (first_year..=now.year()).contains(year))
+        .map(|(year, language)| YearLanguage { year, language })
+        .collect();
+
+    Ok(AllTimeStats {
+        total_commits,
+        years,
+        restricted_commits,
+        years_active,
+        commits_vs_average,
+        language_timeline,
+    })
+}
+
+/// `this_year` against the mean of `earlier` years, in percent above it.
+fn commits_vs_average<'a>(this_year: u64, earlier: impl Iterator<Item = &'a YearCommits>) -> Option<f64> {
+    let (years, commits) = earlier.fold((0u64, 0u64), |(years, commits), y| (years + 1, commits + y.commits));
+    if commits == 0 {
+        return None;
+    }
+    let average = commits as f64 / years as f64;
+    Some((this_year as f64 / average - 1.0) * 100.0)
+}
+
+/// Gathers `username`'s stats, or the `--fallback-user`'s if theirs are empty.
+pub fn gather_stats(
+    http: &dyn Transport,
+    username: &str,
+    config: &Config,
+    generated_at: DateTime<Utc>,
+) -> Result<Stats, Error> {
+    let stats = gather_user_stats(http, username, config, generated_at)?;
+    match &config.fallback_user {
+        Some(fallback) if stats.is_empty() => {
+            eprintln!("warning: {} has nothing to show, rendering --fallback-user {} instead", username, fallback);
+            let mut stats = gather_user_stats(http, fallback, config, generated_at)?;
+            stats.fallback_used = true;
+            Ok(stats)
+        }
+        _ => Ok(stats),
+    }
+}
+
+fn gather_user_stats(
+    http: &dyn Transport,
+    username: &str,
+    config: &Config,
+    generated_at: DateTime<Utc>,
+) -> Result<Stats, Error> {
+    let now = config.counts_until(generated_at);
+    let repo_count = config.lang_metric == LangMetric::RepoCount;
+    let from_contributions = config.lang_from == LangFrom::Contributions;
+    if config.estimate_lines && repo_count {
+        eprintln!("warning: skipping --estimate-lines, it needs --lang-metric bytes");
+    }
+    let user_stats = if StatCategory::GRAPHQL.iter().any(|&category| config.wants(category))
+        || (repo_count && !from_contributions && config.wants(StatCategory::Languages))
+    {
+        match query_user_stats(http, username, config, now) {
+            Ok(user_stats) => user_stats,
+            Err(e @ Error::Http(_)) if !config.strict => {
+                eprintln!("warning: {}; falling back to REST, some stats will be missing", e);
+                return gather_degraded_stats(http, username, config, generated_at);
+            }
+            Err(e) => return Err(e),
+        }
+    } else {
+        User::default()
+    };
+    let selection = if !config.wants(StatCategory::Languages) {
+        RepoSelection::default()
+    } else if from_contributions {
+        query_commit_languages(http, username, config, now)?
+    } else if !repo_count {
+        fetch_repo_languages(http, config)?
+    } else {
+        RepoSelection::default()
+    };
+    let repo_languages = &selection.repos;
+
+    let languages = if !config.wants(StatCategory::Languages) {
+        LanguageStats { languages: Vec::new(), empty_reason: None, estimated_lines: None }
+    } else if repo_count {
+        let repos = if from_contributions {
+            repo_languages.iter().map(largest_language).collect()
+        } else {
+            primary_languages(&user_stats, username, &RepoFilter::new(config)?)
+        };
+        let languages = repo_count_shares(&repos, config, now);
+        let empty_reason = languages.is_empty().then(|| no_primary_languages(&repos));
+        LanguageStats { languages, empty_reason, estimated_lines: None }
+    } else {
+        LanguageStats {
+            languages: language_shares(repo_languages, config, now),
+            empty_reason: selection.no_languages(config, now),
+            estimated_lines: config.estimate_lines.then(|| estimate_lines(repo_languages, config)),
+        }
+    };
+    if config.strict_languages_sum {
+        check_languages_sum(&languages.languages)?;
+    }
+
+    let stars = repo_stars(&user_stats, config);
+    let total_stars = stars.iter().sum();
+    let contributed_repos = contributed_repos(&user_stats);
+    let restricted_included = config.wants(StatCategory::Commits) && include_restricted(http, config);
+    let restricted_commits = match config.commit_mode {
+        CommitMode::Contributions => user_stats.contributions_collection.restricted_contributions_count,
+        CommitMode::DefaultBranch => 0,
+    };
+    let total_commits_this_year = match config.commit_mode {
+        CommitMode::Contributions => user_stats.contributions_collection.commits(restricted_included),
+        CommitMode::DefaultBranch if config.wants(StatCategory::Commits) => {
+            query_default_branch_commits(http, username, config, now)?
+        }
+        CommitMode::DefaultBranch => 0,
+    };
+
+    Ok(Stats {
+        username: username.to_string(),
+        generated_at,
+        total_stars,
+        star_distribution: Some(star_distribution(stars)),
+        total_commits_this_year: Some(total_commits_this_year),
+        restricted_included,
+        restricted_commits,
+        total_prs: user_stats.pull_requests.total_count,
+        total_issues: user_stats.issues.total_count,
+        prs_this_year: user_stats.contributions_collection.total_pull_request_contributions,
+        issues_this_year: user_stats.contributions_collection.total_issue_contributions,
+        contributed_to: Some(user_stats.repositories_contributed_to.total_count),
+        repos_committed_this_year: Some(user_stats.contributions_collection.total_repositories_with_contributed_commits),
+        contributed_repos,
+        top_repos: top_repos(&user_stats, config.top_repos),
+        pinned_repos: pinned_repos(&user_stats),
+        organizations: user_stats
+            .organizations
+            .nodes
+            .iter()
+            .flatten()
+            .map(|org| OrganizationSummary {
+                login: org.login.clone(),
+                avatar_url: org.avatar_url.clone(),
+            })
+            .collect(),
+        account_created: user_stats.account_created_at,
+        stars_given: user_stats.starred_repositories.total_count,
+        languages: languages.languages,
+        languages_empty_reason: languages.empty_reason,
+        estimated_lines: languages.estimated_lines,
+        contributed_languages: if config.contributed_languages && config.wants(StatCategory::Languages) {
+            language_shares(&query_contributed_languages(http, username, config)?, config, now)
+        } else {
+            Vec::new()
+        },
+        all_time: if config.all_time && config.wants(StatCategory::Commits) {
+            Some(gather_all_time(http, username, config, now, repo_languages, restricted_included, user_stats.account_created_at)?)
+        } else {
+            None
+        },
+        degraded: false,
+        fallback_used: false,
+        compare: None,
+        influence_score: Some(influence_score(&user_stats, config, now)),
+        weekday_distribution: weekday_distribution(user_stats.contributions_collection.contribution_calendar.as_ref()),
+        contribution_grid: contribution_grid(user_stats.contributions_collection.contribution_calendar.as_ref()),
+        total_disk_usage: disk_usage(&user_stats),
+        extra: user_stats.extra,
+        external_prs: None,
+        external_issues: None,
+        commits_since_last_run: None,
+        trend: None,
+    }
+    .with_external_contributions(http, config)?
+    .with_commits_since_last_run(http, config, now)?
+    .with_compare(http, config, now)
+    .with_trend(config))
+}
+
+/// Resolves `--count-restricted`. Under `auto`, restricted contributions only count once the
+/// token is confirmed to carry the `repo` scope; fine-grained tokens report no scopes and so
+/// go without, as do `--auth app` installation tokens, which can't be checked on `/user`.
+fn include_restricted(http: &dyn Transport, config: &Config) -> bool {
+    match config.count_restricted {
+        CountRestricted::Always => true,
+        CountRestricted::Never => false,
+        CountRestricted::Auto if config.auth == AuthMode::App => false,
+        CountRestricted::Auto => match check_token(http) {
+            Ok(info) => info.scopes.is_some_and(|scopes| scopes.iter().any(|scope| scope == "repo")),
+            Err(e) => {
+                eprintln!("warning: leaving out restricted contributions, couldn't check the token's scopes: {}", e);
+                false
+            }
+        },
+    }
+}
+
+/// Stars weighted by how alive each repo is:
+///
+/// `sum over owned repos of (stars × star weight + forks × fork weight) × 0.5^(days since push / half-life)`
+///
+/// with the weights and half-life from `--influence-star-weight`, `--influence-fork-weight`
+/// and `--influence-halflife`. A repo pushed today counts in full, one untouched for a
+/// half-life counts half.
+pub fn influence_score(user_stats: &User, config: &Config, now: DateTime<Utc>) -> f64 {
+    user_stats
+        .repositories
+        .nodes
+        .iter()
+        .flatten()
+        .map(|repo| {
+            let reach = repo.stargazer_count as f64 * config.influence_star_weight
+                + repo.fork_count as f64 * config.influence_fork_weight;
+            let recency = match repo.pushed_at {
+                Some(pushed_at) => {
+                    let age_days = (now - pushed_at).num_seconds().max(0) as f64 / 86_400.0;
+                    0.5f64.powf(age_days / config.influence_halflife)
+                }
+                None => 1.0,
+            };
+            reach * recency
+        })
+        .sum()
+}
+
+/// GitHub reports `diskUsage` in kilobytes.
+fn disk_usage(user_stats: &User) -> u64 {
+    user_stats.repositories.nodes.iter().flatten().filter_map(|repo| repo.disk_usage).sum::<u64>() * 1024
+}
+
+/// Each owned repo's stars, less your own under `--exclude-self-stars`.
+fn repo_stars(user_stats: &User, config: &Config) -> Vec<u64> {
+    user_stats
+        .repositories
+        .nodes
+        .iter()
+        .flatten()
+        .map(|repo| {
+            let self_star = config.exclude_self_stars && repo.viewer_has_starred;
+            repo.stargazer_count.saturating_sub(self_star.into())
+        })
+        .collect()
+}
+
+fn star_distribution(mut stars: Vec<u64>) -> StarDistribution {
+    stars.sort_unstable();
+    let middle = stars.len() / 2;
+    let median_stars = match stars.len() {
+        0 => 0.0,
+        len if len % 2 == 0 => (stars[middle - 1] + stars[middle]) as f64 / 2.0,
+        _ => stars[middle] as f64,
+    };
+    let at_least = |floor: u64| stars.iter().filter(|&&count| count >= floor).count() as u64;
+    StarDistribution {
+        median_stars,
+        repos_over_10_stars: at_least(10),
+        repos_over_100_stars: at_least(100),
+        repos_over_1000_stars: at_least(1000),
+    }
+}
+
+impl Stats {
+    /// Whether there's nothing to show: no commits, PRs, issues, stars or languages.
+    pub fn is_empty(&self) -> bool {
+        self.total_commits_this_year.unwrap_or(0) == 0
+            && self.total_prs == 0
+            && self.total_issues == 0
+            && self.total_stars == 0
+            && self.languages.is_empty()
+    }
+
+    /// Attaches the `--compare-user`'s totals. Their failure only costs the comparison, not
+    /// the run.
+    fn with_compare(mut self, http: &dyn Transport, config: &Config, now: DateTime<Utc>) -> Stats {
+        if let Some(login) = &config.compare_user {
+            match query_user_stats(http, login, config, now) {
+                Ok(theirs) => self.compare = Some(self.compare_with(login, &theirs, config)),
+                Err(e) => eprintln!("warning: leaving out --compare-user {}: {}", login, e),
+            }
+        }
+        self
+    }
+
+    /// Attaches `--external-contributions`. A failed search only costs those two counts,
+    /// unless `--strict`.
+    fn with_external_contributions(
+        mut self,
+        http: &dyn Transport,
+        config: &Config,
+    ) -> Result<Stats, Error> {
+        if !config.external_contributions || !(config.wants(StatCategory::Prs) || config.wants(StatCategory::Issues)) {
+            return Ok(self);
+        }
+        match query_external_contributions(http, &self.username) {
+            Ok(external) => {
+                self.external_prs = Some(external.prs);
+                self.external_issues = Some(external.issues);
+            }
+            Err(e) if !config.strict => eprintln!("warning: leaving out external contributions: {}", e),
+            Err(e) => return Err(e),
+        }
+        Ok(self)
+    }
+
+    /// Counts the commits since the last `--history-file` entry, under `--since-last-run`.
+    /// There's nothing to count from on the first run, nor past GitHub's one-year window.
+    fn with_commits_since_last_run(mut self, http: &dyn Transport, config: &Config, now: DateTime<Utc>) -> Result<Stats, Error> {
+        let (true, Some(path)) = (config.since_last_run && config.wants(StatCategory::Commits), &config.history_file) else {
+            return Ok(self);
+        };
+        let Some(last_run) = read_history(path).last().map(|entry| entry.timestamp) else {
+            return Ok(self);
+        };
+        if now - last_run > chrono::Duration::days(365) {
+            eprintln!("warning: skipping --since-last-run, the last run was over a year ago");
+            return Ok(self);
+        }
+        match query_commits_between(http, &self.username, last_run, now, self.restricted_included, config) {
+            Ok(commits) => self.commits_since_last_run = Some(commits),
+            Err(e) if !config.strict => eprintln!("warning: leaving out the commits since the last run: {}", e),
+            Err(e) => return Err(e),
+        }
+        Ok(self)
+    }
+
+    /// Attaches the trend against the `--history-file`, if one is kept.
+    fn with_trend(mut self, config: &Config) -> Stats {
+        if let Some(path) = &config.history_file {
+            self.trend = trend(&read_history(path), &self);
+        }
+        self
+    }
+
+    fn compare_with(&self, login: &str, theirs: &User, config: &Config) -> CompareStats {
+        let delta = |ours: u64, theirs: u64| ours as i64 - theirs as i64;
+        let collection = &theirs.contributions_collection;
+        let total_stars = repo_stars(theirs, config).iter().sum();
+        let total_commits_this_year = collection.commits(self.restricted_included);
+        CompareStats {
+            username: login.to_string(),
+            total_stars,
+            total_commits_this_year,
+            total_prs: theirs.pull_requests.total_count,
+            total_issues: theirs.issues.total_count,
+            contributed_to: theirs.repositories_contributed_to.total_count,
+            stars_given: theirs.starred_repositories.total_count,
+            stars_delta: delta(self.total_stars, total_stars),
+            commits_delta: self.total_commits_this_year.map(|ours| delta(ours, total_commits_this_year)),
+            prs_delta: delta(self.total_prs, theirs.pull_requests.total_count),
+            issues_delta: delta(self.total_issues, theirs.issues.total_count),
+        }
+    }
+}
+
+fn contributed_repos(user_stats: &User) -> Vec<RepoSummary> {
+    let mut contributed_repos: Vec<RepoSummary> = user_stats
+        .repositories_contributed_to
+        .nodes
+        .iter()
+        .flatten()
+        .map(|repo| RepoSummary {
+            name_with_owner: repo.name_with_owner.clone(),
+            stars: repo.stargazer_count,
+        })
+        .collect();
+    contributed_repos.sort_by(|a, b| b.stars.cmp(&a.stars).then_with(|| a.name_with_owner.cmp(&b.name_with_owner)));
+    contributed_repos
+}
+
+/// Ranks every owned repo, all pages of them, by stars; ties go alphabetically.
+fn top_repos(user_stats: &User, count: usize) -> Vec<TopRepo> {
+    let mut repos: Vec<TopRepo> = user_stats
+        .repositories
+        .nodes
+        .iter()
+        .flatten()
+        .map(|repo| TopRepo {
+            name: repo.name.clone(),
+            description: repo.description.clone(),
+            stars: repo.stargazer_count,
+            language: repo.primary_language.as_ref().map(|language| language.name.clone()),
+            language_color: repo.primary_language.as_ref().and_then(|language| language.color.clone()),
+        })
+        .collect();
+    repos.sort_by(|a, b| b.stars.cmp(&a.stars).then_with(|| a.name.cmp(&b.name)));
+    repos.truncate(count);
+    repos
+}
+
+fn pinned_repos(user_stats: &User) -> Vec<PinnedRepo> {
+    user_stats
+        .pinned_items
+        .nodes
+        .iter()
+        .flatten()
+        .map(|repo| PinnedRepo {
+            name: repo.name.clone(),
+            description: repo.description.clone(),
+            stars: repo.stargazer_count,
+            language: repo.primary_language.as_ref().map(|language| language.name.clone()),
+            language_color: repo.primary_language.as_ref().and_then(|language| language.color.clone()),
+        })
+        .collect()
+}
+
+/// The owned repos' primary languages, for `--lang-metric repo-count`, less the ones
+/// `--exclude-repo` and `--exclude-repo-pattern` name.
+fn primary_languages(user_stats: &User, username: &str, filter: &RepoFilter) -> Vec<RepoPrimaryLanguage> {
+    user_stats
+        .repositories
+        .nodes
+        .iter()
+        .flatten()
+        .filter(|repo| !filter.excludes_name(&format!("{}/{}", username, repo.name)))
+        .map(|repo| RepoPrimaryLanguage {
+            language: repo.primary_language.as_ref().map(|language| language.name.clone()),
+            pushed_at: repo.pushed_at,
+        })
+        .collect()
+}
+
+/// A repo counted for the language it has the most bytes of, which is how GitHub picks a
+/// primary language.
+fn largest_language(repo: &RepoLanguages) -> RepoPrimaryLanguage {
+    let largest = repo.bytes.iter().filter(|(_, bytes)| **bytes > 0).max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)));
+    RepoPrimaryLanguage {
+        language: largest.map(|(language, _)| language.clone()),
+        pushed_at: repo.pushed_at,
+    }
+}
+
+/// What can still be gathered over REST alone while GraphQL is down.
+fn gather_degraded_stats(
+    http: &dyn Transport,
+    username: &str,
+    config: &Config,
+    generated_at: DateTime<Utc>,
+) -> Result<Stats, Error> {
+    let now = config.counts_until(generated_at);
+    let rest = query_rest_stats(http, username, config, now)?;
+    if config.exclude_self_stars {
+        eprintln!("warning: ignoring --exclude-self-stars, REST doesn't say which repos you starred");
+    }
+    if config.all_time {
+        eprintln!("warning: skipping --all-time, it needs GraphQL");
+    }
+    if config.contributed_languages {
+        eprintln!("warning: skipping --contributed-languages, it needs GraphQL");
+    }
+    let languages = if config.wants(StatCategory::Languages) {
+        calculate_language_stats(http, username, config, now)?
+    } else {
+        LanguageStats { languages: Vec::new(), empty_reason: None, estimated_lines: None }
+    };
+    if config.strict_languages_sum {
+        check_languages_sum(&languages.languages)?;
+    }
+    Ok(Stats {
+        username: username.to_string(),
+        generated_at,
+        total_stars: rest.total_stars,
+        star_distribution: None,
+        total_commits_this_year: None,
+        restricted_included: false,
+        restricted_commits: 0,
+        total_prs: rest.total_prs,
+        total_issues: rest.total_issues,
+        prs_this_year: rest.prs_this_year,
+        issues_this_year: rest.issues_this_year,
+        contributed_to: None,
+        repos_committed_this_year: None,
+        contributed_repos: Vec::new(),
+        top_repos: Vec::new(),
+        pinned_repos: Vec::new(),
+        organizations: Vec::new(),
+        account_created: None,
+        stars_given: rest.stars_given,
+        languages: languages.languages,
+        languages_empty_reason: languages.empty_reason,
+        estimated_lines: languages.estimated_lines,
+        contributed_languages: Vec::new(),
+        all_time: None,
+        degraded: true,
+        fallback_used: false,
+        compare: None,
+        extra: serde_json::Map::new(),
+        influence_score: None,
+        weekday_distribution: Vec::new(),
+        contribution_grid: String::new(),
+        total_disk_usage: rest.total_disk_usage * 1024,
+        external_prs: None,
+        external_issues: None,
+        commits_since_last_run: None,
+        trend: None,
+    }
+    .with_external_contributions(http, config)?
+    .with_compare(http, config, now)
+    .with_trend(config))
+}
@@ -0,0 +1,86 @@
+use crate::config::Config;
+use crate::http::Transport;
+use crate::render::{abbreviate_number, bar_full_scale, format_lang_name, format_percentage, render_progress_bar};
+use crate::stats::{Stats, gather_stats};
+use chrono::prelude::*;
+use ratatui::Frame;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph};
+
+const ACCENT: Color = Color::Rgb(0xe5, 0x6b, 0x9f);
+
+/// Previews the card in the terminal until `q` (or Esc) is pressed; `r` gathers fresh stats.
+pub fn run_tui(
+    http: &dyn Transport,
+    username: &str,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let gather = || gather_stats(http, username, config, config.now.unwrap_or_else(Utc::now));
+    let mut stats = gather()?;
+    let mut status = format!("fetched {}", stats.generated_at.format("%H:%M:%S UTC"));
+
+    let mut terminal = ratatui::init();
+    let result = loop {
+        if let Err(e) = terminal.draw(|frame| draw(frame, &stats, config, &status)) {
+            break Err(e.into());
+        }
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => key,
+            Ok(_) => continue,
+            Err(e) => break Err(e.into()),
+        };
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => break Ok(()),
+            KeyCode::Char('r') => match gather() {
+                Ok(fresh) => {
+                    stats = fresh;
+                    status = format!("refreshed {}", stats.generated_at.format("%H:%M:%S UTC"));
+                }
+                Err(e) => status = format!("refresh failed: {}", e),
+            },
+            _ => {}
+        }
+    };
+    ratatui::restore();
+    result
+}
+
+fn draw(frame: &mut Frame, stats: &Stats, config: &Config, status: &str) {
+    let unavailable = |value: Option<u64>| value.map(|n| abbreviate_number(n, config)).unwrap_or_else(|| "n/a".to_string());
+    let mut lines = vec![
+        stat_line("stars across repos", abbreviate_number(stats.total_stars, config)),
+        stat_line("commits this year", unavailable(stats.total_commits_this_year)),
+        stat_line("total pull requests", abbreviate_number(stats.total_prs, config)),
+        stat_line("total issues", abbreviate_number(stats.total_issues, config)),
+        stat_line("repos contributed to", unavailable(stats.contributed_to)),
+        stat_line("repos starred", abbreviate_number(stats.stars_given, config)),
+        Line::default(),
+    ];
+
+    let full_scale = bar_full_scale(config.bar_scale, &stats.languages);
+    for (lang, percentage) in &stats.languages {
+        lines.push(Line::from(vec![
+            format!("{:<15} ", format_lang_name(lang)).into(),
+            render_progress_bar(*percentage, full_scale, config.bar_direction, None).fg(ACCENT),
+            format!(" {}", format_percentage(*percentage, config.lang_precision.into())).into(),
+        ]));
+    }
+
+    let [card, footer] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+    let title = format!(" {}'s github stats ", stats.username);
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(title.fg(ACCENT).bold())),
+        card,
+    );
+    frame.render_widget(
+        Paragraph::new(format!("r refresh · q quit · {}", status)).style(Style::new().dim()),
+        footer,
+    );
+}
+
+fn stat_line(label: &str, value: String) -> Line<'static> {
+    Line::from(vec![format!("{}: ", label).into(), value.bold()])
+}
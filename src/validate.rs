@@ -0,0 +1,53 @@
+use crate::config::Format;
+use resvg::usvg;
+
+/// Checks that rendered output is well-formed before it gets written, so a template bug
+/// fails the run instead of landing a broken card on the profile. Markdown, SVG and JSON are
+/// checked; PNG output already went through the SVG parser while rasterizing.
+pub fn validate_output(format: Format, rendered: &[u8]) -> Result<(), String> {
+    match format {
+        Format::Markdown => validate_markdown(&text(rendered)?),
+        Format::Svg => validate_svg(&text(rendered)?),
+        Format::Json => validate_json(&text(rendered)?),
+        Format::Png | Format::Html | Format::Prometheus | Format::Openmetrics | Format::Oneline | Format::Shields => Ok(()),
+    }
+}
+
+fn text(rendered: &[u8]) -> Result<String, String> {
+    String::from_utf8(rendered.to_vec()).map_err(|_| "output is not valid UTF-8".to_string())
+}
+
+pub fn validate_svg(svg: &str) -> Result<(), String> {
+    usvg::Tree::from_str(svg, &usvg::Options::default())
+        .map(|_| ())
+        .map_err(|e| format!("invalid SVG: {}", e))
+}
+
+/// A `.json.tera` template can render anything, so its output has to parse.
+pub fn validate_json(json: &str) -> Result<(), String> {
+    serde_json::from_str::<serde_json::Value>(json)
+        .map(|_| ())
+        .map_err(|e| format!("invalid JSON: {}", e))
+}
+
+/// Code fences must pair up, and no Tera delimiters may survive into the output.
+pub fn validate_markdown(markdown: &str) -> Result<(), String> {
+    let mut open_fence = None;
+    for (number, line) in markdown.lines().enumerate() {
+        if line.trim_start().starts_with("```") {
+            open_fence = match open_fence {
+                Some(_) => None,
+                None => Some(number + 1),
+            };
+        }
+        for delimiter in ["{{", "}}", "{%", "%}"] {
+            if line.contains(delimiter) {
+                return Err(format!("line {}: unrendered template syntax `{}`", number + 1, delimiter));
+            }
+        }
+    }
+    match open_fence {
+        Some(line) => Err(format!("line {}: code fence is never closed", line)),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use chrono::prelude::*;
+use common::FixtureTransport;
+use std::error::Error;
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use whoami::cache::{CachingTransport, cache_info, clear_cache, prune_cache};
+use whoami::config::Config;
+use whoami::http::{Response, Transport};
+use whoami::stats::gather_stats;
+
+/// Counts the requests that get past the cache.
+struct CountingTransport {
+    inner: FixtureTransport,
+    requests: AtomicUsize,
+}
+
+impl Transport for CountingTransport {
+    fn get(&self, url: &str) -> Result<Response, Box<dyn Error>> {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        self.inner.get(url)
+    }
+
+    fn post_json(&self, url: &str, body: &serde_json::Value) -> Result<Response, Box<dyn Error>> {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        self.inner.post_json(url, body)
+    }
+}
+
+fn cache_config(name: &str) -> Config {
+    let dir = std::env::temp_dir().join(format!("whoami-cache-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    Config {
+        cache: true,
+        cache_dir: Some(dir),
+        ..Config::default()
+    }
+}
+
+#[test]
+fn second_run_is_served_from_the_cache() {
+    let config = cache_config("reuse");
+    let counting = CountingTransport { inner: FixtureTransport::new("many_languages"), requests: AtomicUsize::new(0) };
+    let http = CachingTransport::new(&counting, &config);
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+
+    let first = gather_stats(&http, "octocat", &config, now).unwrap();
+    let fetched = counting.requests.load(Ordering::SeqCst);
+    assert!(fetched > 0);
+    let second = gather_stats(&http, "octocat", &config, now).unwrap();
+    assert_eq!(counting.requests.load(Ordering::SeqCst), fetched);
+    assert_eq!(second.total_stars, first.total_stars);
+
+    let (entries, bytes) = cache_info(&config.cache_dir()).unwrap();
+    assert!(entries > 0 && bytes > 0);
+    assert_eq!(clear_cache(&config.cache_dir()).unwrap(), entries);
+    assert_eq!(cache_info(&config.cache_dir()).unwrap(), (0, 0));
+    gather_stats(&http, "octocat", &config, now).unwrap();
+    assert_eq!(counting.requests.load(Ordering::SeqCst), fetched * 2);
+    fs::remove_dir_all(config.cache_dir()).unwrap();
+}
+
+#[test]
+fn prune_removes_old_and_unreadable_entries() {
+    let config = cache_config("prune");
+    let dir = config.cache_dir();
+    let fixtures = FixtureTransport::new("many_languages");
+    let http = CachingTransport::new(&fixtures, &config);
+    gather_stats(&http, "octocat", &config, Utc::now()).unwrap();
+    let (entries, _) = cache_info(&dir).unwrap();
+    fs::write(dir.join("garbage.json"), "{").unwrap();
+
+    assert_eq!(prune_cache(&dir, chrono::Duration::hours(1), Utc::now()).unwrap(), 1);
+    assert_eq!(cache_info(&dir).unwrap().0, entries);
+    let later = Utc::now() + chrono::Duration::hours(2);
+    assert_eq!(prune_cache(&dir, chrono::Duration::hours(1), later).unwrap(), entries);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn missing_cache_dir_is_empty() {
+    let config = cache_config("missing");
+    assert_eq!(cache_info(&config.cache_dir()).unwrap(), (0, 0));
+    assert_eq!(clear_cache(&config.cache_dir()).unwrap(), 0);
+}
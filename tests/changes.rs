@@ -0,0 +1,28 @@
+mod common;
+
+use chrono::prelude::*;
+use common::FixtureTransport;
+use whoami::changes::summarize_changes;
+use whoami::config::Config;
+use whoami::stats::{Stats, gather_stats};
+
+fn stats(case: &str) -> Stats {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+    gather_stats(&FixtureTransport::new(case), "octocat", &Config::default(), now).unwrap()
+}
+
+#[test]
+fn summary_names_the_numbers_that_moved() {
+    let before = stats("many_languages");
+    let mut after = before.clone();
+    after.total_stars = 2400;
+    after.total_prs += 3;
+    assert_eq!(summarize_changes(Some(&before), &after, &Config::default()), "Stats update: stars 2.3k→2.4k, prs 312→315");
+}
+
+#[test]
+fn summary_without_changes_or_history() {
+    let current = stats("many_languages");
+    assert_eq!(summarize_changes(Some(&current), &current, &Config::default()), "Stats update: no visible changes");
+    assert_eq!(summarize_changes(None, &current, &Config::default()), "Stats update: first snapshot");
+}
@@ -0,0 +1,63 @@
+use clap::Parser;
+use std::collections::HashMap;
+use std::error::Error;
+use whoami::colors::{ColorSource, LINGUIST_LANGUAGES_URL, language_color, load_remote_colors, parse_linguist_colors};
+use whoami::config::Config;
+use whoami::http::{Response, Transport};
+
+const LANGUAGES_YML: &str = r##"# Defines all Languages known to GitHub.
+---
+"1C Enterprise":
+  type: programming
+  color: "#814CCC"
+Rust:
+  type: programming
+  color: "#dea583"
+  aliases:
+  - rs
+Text:
+  type: prose
+"##;
+
+/// Serves `languages.yml`, or fails every request when `body` is `None`.
+struct LinguistTransport {
+    body: Option<&'static str>,
+}
+
+impl Transport for LinguistTransport {
+    fn get(&self, url: &str) -> Result<Response, Box<dyn Error>> {
+        assert_eq!(url, LINGUIST_LANGUAGES_URL);
+        match self.body {
+            Some(body) => Ok(Response { status: 200, headers: HashMap::new(), body: body.to_string() }),
+            None => Err("connection reset".into()),
+        }
+    }
+
+    fn post_json(&self, _: &str, _: &serde_json::Value) -> Result<Response, Box<dyn Error>> {
+        Err("unexpected".into())
+    }
+}
+
+#[test]
+fn linguist_colors_parse_from_languages_yml() {
+    let colors = parse_linguist_colors(LANGUAGES_YML);
+    assert_eq!(colors.len(), 2);
+    assert_eq!(colors["1C Enterprise"], "#814CCC");
+    assert_eq!(colors["Rust"], "#dea583");
+}
+
+#[test]
+fn remote_colors_fall_back_to_the_cached_copy_then_the_built_ins() {
+    let dir = std::env::temp_dir().join(format!("whoami-colors-{}", std::process::id()));
+    let config = Config::parse_from(["whoami", "--remote-colors", "--cache-dir", dir.to_str().unwrap()]);
+    let offline = LinguistTransport { body: None };
+
+    assert_eq!(load_remote_colors(&offline, &config), ColorSource::BuiltIn);
+    assert_eq!(language_color("Rust"), Some("#dea584"));
+
+    assert_eq!(load_remote_colors(&LinguistTransport { body: Some(LANGUAGES_YML) }, &config), ColorSource::Fetched);
+    assert_eq!(load_remote_colors(&offline, &config), ColorSource::Cached(dir.join("languages.yml")));
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(language_color("Rust"), Some("#dea583"));
+    assert_eq!(language_color("Go"), Some("#00ADD8"));
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use whoami::github::GRAPHQL_URL;
+use whoami::http::{Response, Transport};
+
+/// Replays a recorded account from `tests/fixtures/<case>`:
+/// `graphql.json` answers the GraphQL query (or `graphql/<Operation>.json` a named operation,
+/// when recorded), `repos.json` is the first page of the REST repo listing (later pages are
+/// empty), `repos/<repo>.json` answers a lookup of one repo by name, and
+/// `languages/<repo>.json` answers each repo's `languages_url`. For the REST
+/// fallback and external contributions, `search/<pr|issue>[_this_year|_external].json`
+/// answers issue searches, `starred.json` the starred listing and `users/<login>.json` a
+/// lookup of one user or bot. Anything else, including a
+/// missing file, is a 404.
+pub struct FixtureTransport {
+    dir: PathBuf,
+}
+
+impl FixtureTransport {
+    pub fn new(case: &str) -> Self {
+        FixtureTransport { dir: fixture_dir(case) }
+    }
+
+    fn file(&self, relative: &str) -> Response {
+        match fs::read_to_string(self.dir.join(relative)) {
+            Ok(body) => Response { status: 200, headers: HashMap::new(), body },
+            Err(_) => not_found(),
+        }
+    }
+}
+
+impl Transport for FixtureTransport {
+    fn get(&self, url: &str) -> Result<Response, Box<dyn Error>> {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        if path == "https://api.github.com/user/repos" {
+            let first_page = query.split('&').all(|param| !param.starts_with("page=") || param == "page=1");
+            return Ok(if first_page {
+                self.file("repos.json")
+            } else {
+                Response { status: 200, headers: HashMap::new(), body: "[]".to_string() }
+            });
+        }
+        if path == "https://api.github.com/installation/repositories" {
+            let first_page = query.split('&').all(|param| !param.starts_with("page=") || param == "page=1");
+            return Ok(if first_page {
+                self.file("installation-repositories.json")
+            } else {
+                Response { status: 200, headers: HashMap::new(), body: r#"{"total_count":0,"repositories":[]}"#.to_string() }
+            });
+        }
+        if let Some(repo) = path
+            .strip_prefix("https://api.github.com/repos/")
+            .and_then(|rest| rest.strip_suffix("/languages"))
+        {
+            let name = repo.rsplit('/').next().unwrap_or(repo);
+            return Ok(self.file(&format!("languages/{}.json", name)));
+        }
+        if let Some((_, name)) = path.strip_prefix("https://api.github.com/repos/").and_then(|repo| repo.split_once('/')) {
+            return Ok(self.file(&format!("repos/{}.json", name)));
+        }
+        if path == "https://api.github.com/search/issues" {
+            let kind = if query.contains("type:pr") { "pr" } else { "issue" };
+            let window = if query.contains("created:") {
+                "_this_year"
+            } else if query.contains("-user:") {
+                "_external"
+            } else {
+                ""
+            };
+            return Ok(self.file(&format!("search/{}{}.json", kind, window)));
+        }
+        if path.starts_with("https://api.github.com/users/") && path.ends_with("/starred") {
+            return Ok(self.file("starred.json"));
+        }
+        if let Some(login) = path.strip_prefix("https://api.github.com/users/") {
+            return Ok(self.file(&format!("users/{}.json", login)));
+        }
+        Ok(not_found())
+    }
+
+    fn post_json(&self, url: &str, body: &serde_json::Value) -> Result<Response, Box<dyn Error>> {
+        if url != GRAPHQL_URL {
+            return Ok(not_found());
+        }
+        let query = body["query"].as_str().unwrap_or_default();
+        let operation = query
+            .split_once("query ")
+            .and_then(|(_, rest)| rest.split(|c: char| !c.is_alphanumeric()).next())
+            .filter(|name| !name.is_empty());
+        if let Some(operation) = operation {
+            // Per-login answers, for ops like `AuthorId` that look logins up.
+            if let Some(login) = body["variables"]["login"].as_str() {
+                let relative = format!("graphql/{}/{}.json", operation, login);
+                if self.dir.join(&relative).exists() {
+                    return Ok(self.file(&relative));
+                }
+            }
+            let relative = format!("graphql/{}.json", operation);
+            if self.dir.join(&relative).exists() {
+                return Ok(self.file(&relative));
+            }
+        }
+        Ok(self.file("graphql.json"))
+    }
+}
+
+pub fn fixture_dir(case: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(case)
+}
+
+fn not_found() -> Response {
+    Response {
+        status: 404,
+        headers: HashMap::new(),
+        body: r#"{"message":"Not Found"}"#.to_string(),
+    }
+}
@@ -0,0 +1,96 @@
+use clap::{CommandFactory, FromArgMatches, Parser};
+use whoami::config::{Cli, Config, Format};
+
+#[test]
+fn config_print_round_trips_and_redacts_tokens() {
+    let config = Config::parse_from(["whoami", "--top-repos", "3", "--token-command", "echo ghp_0123456789abcdef", "--only", "stars,prs"]);
+    let printed = config.to_toml().unwrap();
+    let table: toml::Table = printed.parse().unwrap();
+    assert_eq!(table["top-repos"].as_integer(), Some(3));
+    assert_eq!(table["token-command"].as_str(), Some("echo <redacted>"));
+    assert!(!printed.contains("ghp_"));
+    assert!(table.get("config-print").is_none());
+
+    let reparsed: Config = toml::Table::try_from(&config).unwrap().try_into().unwrap();
+    assert_eq!(reparsed.to_toml().unwrap(), printed);
+}
+
+fn resolve(args: &[&str]) -> Result<Config, String> {
+    let matches = Cli::command().get_matches_from(args);
+    Cli::from_arg_matches(&matches).unwrap().config.with_file(&matches)
+}
+
+#[test]
+fn template_file_extension_picks_the_format() {
+    assert_eq!(resolve(&["whoami", "--template-file", "cards/card.svg.tera"]).unwrap().format, Format::Svg);
+    assert_eq!(resolve(&["whoami", "--template-file", "profile.md.tera"]).unwrap().format, Format::Markdown);
+    assert_eq!(resolve(&["whoami", "--template-file", "card.svg.tera", "--format", "png"]).unwrap().format, Format::Png);
+    assert_eq!(resolve(&["whoami", "--template-file", "notes.txt.tera", "--format", "svg"]).unwrap().format, Format::Svg);
+
+    assert_eq!(resolve(&["whoami", "--template-file", "page.html.tera"]).unwrap().format, Format::Html);
+    assert_eq!(resolve(&["whoami", "--template-file", "stats.json.tera"]).unwrap().format, Format::Json);
+    assert_eq!(resolve(&["whoami", "--template-file", "stats.json.tera", "--format", "json"]).unwrap().format, Format::Json);
+
+    let err = resolve(&["whoami", "--template-file", "card.svg.tera", "--format", "markdown"]).unwrap_err();
+    assert_eq!(err, "--template-file card.svg.tera renders svg output, but --format is markdown");
+    let err = resolve(&["whoami", "--template-file", "page.html.tera", "--format", "svg"]).unwrap_err();
+    assert_eq!(err, "--template-file page.html.tera renders html output, but --format is svg");
+    let err = resolve(&["whoami", "--template-file", "stats.json.tera", "--format", "markdown"]).unwrap_err();
+    assert_eq!(err, "--template-file stats.json.tera renders json output, but --format is markdown");
+    let err = resolve(&["whoami", "--template-file", "notes.txt.tera"]).unwrap_err();
+    assert_eq!(err, "--template-file notes.txt.tera renders no format by its extension (.md.tera, .svg.tera, .html.tera or .json.tera); pass --format");
+    let err = resolve(&["whoami", "--template-file", "badge.json.tera", "--format", "shields"]).unwrap_err();
+    assert_eq!(err, "--format shields renders no template, so --template-file badge.json.tera would go unused");
+    let err = resolve(&["whoami", "--format", "html"]).unwrap_err();
+    assert_eq!(err, "--format html bundles no template; pass --template-file or --template-string");
+}
+
+#[test]
+fn config_file_values_pass_the_flags_checks() {
+    let path = std::env::temp_dir().join(format!("whoami-checked-{}.toml", std::process::id()));
+    let with_file = |text: &str| {
+        std::fs::write(&path, text).unwrap();
+        resolve(&["whoami", "--config", path.to_str().unwrap()])
+    };
+    let err = with_file("lang-recency-halflife = 0\n").unwrap_err();
+    assert!(err.ends_with("lang-recency-halflife = 0: expected a positive number of days"), "{}", err);
+    let err = with_file("regression-threshold = 150.0\n").unwrap_err();
+    assert!(err.ends_with("regression-threshold = 150.0: expected a percentage from 0 to 100"), "{}", err);
+    assert!(with_file("only = [\"stars\", \"bogus\"]\n").unwrap_err().contains("only = \"bogus\""));
+
+    // A flag's requirements aren't checked value by value.
+    let config = with_file("lang-recency-halflife = 30.5\nfit-output = true\nmax-output-bytes = 4096\n").unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!((config.lang_recency_halflife, config.max_output_bytes), (Some(30.5), Some(4096)));
+}
+
+#[test]
+fn headers_parse_as_name_and_value() {
+    let headers = whoami::http::parse_headers(&["X-Internal-Auth: abc 123".to_string(), "authorization: token x".to_string()]).unwrap();
+    assert_eq!(headers.len(), 1);
+    assert_eq!(headers["x-internal-auth"], "abc 123");
+
+    assert_eq!(whoami::http::parse_headers(&["X-Route".to_string()]).unwrap_err(), r#"invalid --header "X-Route": expected Name: Value"#);
+    assert_eq!(whoami::http::parse_headers(&["Bad Name: 1".to_string()]).unwrap_err(), r#"invalid --header name "Bad Name""#);
+}
+
+#[test]
+fn a_request_past_the_request_timeout_fails() {
+    use whoami::http::Transport;
+    // Accepts the connection and never answers.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/user", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        let _held = listener.accept();
+        std::thread::sleep(std::time::Duration::from_secs(5));
+    });
+    let http = whoami::http::HttpTransport::new("token")
+        .with_client_options(Default::default(), std::time::Duration::from_millis(200))
+        .unwrap();
+    let started = std::time::Instant::now();
+    assert!(http.get(&url).is_err());
+    assert!(started.elapsed() < std::time::Duration::from_secs(3));
+
+    assert_eq!(resolve(&["whoami"]).unwrap().request_timeout, 30);
+    assert!(Config::try_parse_from(["whoami", "--request-timeout", "0"]).is_err());
+}
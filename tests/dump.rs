@@ -0,0 +1,57 @@
+mod common;
+
+use chrono::prelude::*;
+use common::FixtureTransport;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use whoami::config::Config;
+use whoami::dump::DumpingTransport;
+use whoami::http::{Response, Transport};
+use whoami::stats::gather_stats;
+
+/// Answers everything with a body that echoes a token.
+struct EchoingTransport;
+
+impl Transport for EchoingTransport {
+    fn get(&self, _: &str) -> Result<Response, Box<dyn Error>> {
+        let body = r#"{"message":"token ghp_abc123XYZ is bad","hint":"github_pat_11AB_cd"}"#.to_string();
+        Ok(Response { status: 401, headers: HashMap::new(), body })
+    }
+
+    fn post_json(&self, url: &str, _: &serde_json::Value) -> Result<Response, Box<dyn Error>> {
+        self.get(url)
+    }
+}
+
+fn dump_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("whoami-dump-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn every_response_is_dumped_under_its_request() {
+    let dir = dump_dir("run");
+    let inner = FixtureTransport::new("many_languages");
+    let http = DumpingTransport::new(&inner, dir.clone());
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+    gather_stats(&http, "octocat", &Config::default(), now).unwrap();
+
+    let mut names: Vec<String> = fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned()).collect();
+    names.sort();
+    assert_eq!(names[0], "001-graphql.json");
+    let engine = names.iter().find(|name| name.ends_with("-repos-octocat-engine-languages.json")).unwrap();
+    assert_eq!(fs::read_to_string(dir.join(engine)).unwrap(), fs::read_to_string(common::fixture_dir("many_languages").join("languages/engine.json")).unwrap());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dumped_bodies_have_tokens_redacted() {
+    let dir = dump_dir("redact");
+    let http = DumpingTransport::new(&EchoingTransport, dir.clone());
+    http.post_json(whoami::github::GRAPHQL_URL, &serde_json::json!({ "query": "query AuthorId($login: String!) { x }" })).unwrap();
+    let dumped = fs::read_to_string(dir.join("001-graphql-AuthorId.json")).unwrap();
+    assert_eq!(dumped, r#"{"message":"token <redacted> is bad","hint":"<redacted>"}"#);
+    fs::remove_dir_all(&dir).unwrap();
+}
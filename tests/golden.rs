@@ -0,0 +1,80 @@
+mod common;
+
+use chrono::prelude::*;
+use common::{FixtureTransport, fixture_dir};
+use std::fs;
+use tera::Tera;
+use whoami::config::Config;
+use whoami::metrics::render_prometheus;
+use whoami::render::{load_templates, render_readme, render_svg};
+use whoami::stats::Stats;
+use whoami::stats::gather_stats;
+
+/// Renders a template for a recorded account and compares it byte-for-byte with
+/// `tests/fixtures/<case>/<golden>`. Run with `UPDATE_GOLDEN=1` to rewrite the goldens.
+fn assert_golden_with(case: &str, golden: &str, render: fn(&Tera, &Stats, &Config) -> tera::Result<String>) {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+    let config = Config::default();
+    let stats = gather_stats(&FixtureTransport::new(case), "octocat", &config, now).unwrap();
+    let tera = load_templates(concat!(env!("CARGO_MANIFEST_DIR"), "/templates/**/*.tera"), &config).unwrap();
+    let rendered = render(&tera, &stats, &config).unwrap();
+
+    let golden = fixture_dir(case).join(golden);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&golden, &rendered).unwrap();
+    }
+    assert_eq!(rendered, fs::read_to_string(&golden).unwrap(), "golden mismatch for {}", case);
+}
+
+fn assert_golden(case: &str) {
+    assert_golden_with(case, "README.md", render_readme);
+}
+
+#[test]
+fn renders_account_with_many_languages() {
+    assert_golden("many_languages");
+}
+
+#[test]
+fn renders_account_with_no_languages() {
+    assert_golden("no_languages");
+}
+
+#[test]
+fn renders_account_with_single_repo() {
+    assert_golden("single_repo");
+}
+
+#[test]
+fn renders_svg_card() {
+    assert_golden_with("many_languages", "stats.svg", render_svg);
+}
+
+#[test]
+fn rasterized_card_size_follows_scale() {
+    let svg = std::fs::read_to_string(fixture_dir("many_languages").join("stats.svg")).unwrap();
+    let png = whoami::raster::rasterize(&svg, 2.0).unwrap();
+    // The IHDR chunk holds the big-endian width and height right after the PNG signature.
+    let width = u32::from_be_bytes(png[16..20].try_into().unwrap());
+    let height = u32::from_be_bytes(png[20..24].try_into().unwrap());
+    assert_eq!((width, height), (990, 742));
+}
+
+#[test]
+fn renders_prometheus_metrics() {
+    assert_golden_with("many_languages", "metrics.prom", |_, stats, config| Ok(render_prometheus(stats, config)));
+}
+
+#[test]
+fn renders_oneline_summary() {
+    assert_golden_with("many_languages", "oneline.txt", |_, stats, config| {
+        Ok(whoami::oneline::render_oneline(stats, &config.oneline_format, config).unwrap())
+    });
+}
+
+#[test]
+fn renders_top_language_shields_badge() {
+    assert_golden_with("many_languages", "shields.json", |_, stats, config| {
+        Ok(whoami::shields::render_shields(stats, whoami::config::Badge::TopLanguage, config))
+    });
+}
@@ -0,0 +1,486 @@
+mod common;
+
+use chrono::prelude::*;
+use clap::Parser;
+use common::FixtureTransport;
+use whoami::config::Config;
+use std::sync::Mutex;
+use whoami::error::Error;
+use whoami::http::{Response, Transport};
+use whoami::stats::gather_stats;
+
+/// Keeps the GraphQL queries sent and their variables, to check what they select.
+struct RecordingTransport {
+    inner: FixtureTransport,
+    queries: Mutex<Vec<String>>,
+    variables: Mutex<Vec<serde_json::Value>>,
+}
+
+impl RecordingTransport {
+    fn new(case: &str) -> Self {
+        RecordingTransport { inner: FixtureTransport::new(case), queries: Mutex::new(Vec::new()), variables: Mutex::new(Vec::new()) }
+    }
+}
+
+impl Transport for RecordingTransport {
+    fn get(&self, url: &str) -> Result<Response, Box<dyn std::error::Error>> {
+        self.inner.get(url)
+    }
+
+    fn post_json(&self, url: &str, body: &serde_json::Value) -> Result<Response, Box<dyn std::error::Error>> {
+        self.queries.lock().unwrap().push(body["query"].as_str().unwrap_or_default().to_string());
+        self.variables.lock().unwrap().push(body["variables"].clone());
+        self.inner.post_json(url, body)
+    }
+}
+
+fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap()
+}
+
+#[test]
+fn field_errors_keep_the_partial_data() {
+    let stats = gather_stats(&FixtureTransport::new("partial_data"), "octocat", &Config::default(), now()).unwrap();
+    assert_eq!(stats.total_stars, 42);
+    assert_eq!(stats.total_prs, 4);
+}
+
+#[test]
+fn strict_mode_fails_on_any_error() {
+    let config = Config::parse_from(["whoami", "--strict"]);
+    let err = gather_stats(&FixtureTransport::new("partial_data"), "octocat", &config, now()).unwrap_err();
+    assert!(matches!(&err, Error::GraphQl(messages) if messages[0].contains("Could not resolve to a Repository")), "{}", err);
+}
+
+#[test]
+fn rest_fallback_when_graphql_is_down() {
+    let stats = gather_stats(&FixtureTransport::new("graphql_down"), "octocat", &Config::default(), now()).unwrap();
+    assert!(stats.degraded);
+    assert_eq!(stats.total_stars, 42);
+    assert_eq!((stats.total_prs, stats.prs_this_year), (17, 5));
+    assert_eq!((stats.total_issues, stats.issues_this_year), (9, 2));
+    assert_eq!(stats.stars_given, 3);
+    assert_eq!(stats.total_commits_this_year, None);
+    assert_eq!(stats.contributed_to, None);
+    assert_eq!(stats.repos_committed_this_year, None);
+}
+
+#[test]
+fn repos_committed_this_year_counts_distinct_repos() {
+    let stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &Config::default(), now()).unwrap();
+    assert_eq!(stats.repos_committed_this_year, Some(37));
+    // A quiet year leaves the field out of the response entirely.
+    let stats = gather_stats(&FixtureTransport::new("no_languages"), "octocat", &Config::default(), now()).unwrap();
+    assert_eq!(stats.repos_committed_this_year, Some(0));
+}
+
+#[test]
+fn strict_mode_skips_the_rest_fallback() {
+    let config = Config::parse_from(["whoami", "--strict"]);
+    let err = gather_stats(&FixtureTransport::new("graphql_down"), "octocat", &config, now()).unwrap_err();
+    assert!(matches!(err, Error::Http(_)), "{}", err);
+}
+
+#[test]
+fn unknown_users_are_their_own_error() {
+    let err = gather_stats(&FixtureTransport::new("missing_user"), "octocat", &Config::default(), now()).unwrap_err();
+    assert!(matches!(&err, Error::UserNotFound(login) if login == "octocat"), "{:?}", err);
+}
+
+#[test]
+fn self_stars_are_only_subtracted_when_asked() {
+    let http = FixtureTransport::new("many_languages");
+    let stats = gather_stats(&http, "octocat", &Config::default(), now()).unwrap();
+    assert_eq!(stats.total_stars, 2259);
+
+    let config = Config::parse_from(["whoami", "--exclude-self-stars"]);
+    let stats = gather_stats(&http, "octocat", &config, now()).unwrap();
+    assert_eq!(stats.total_stars, 2257);
+}
+
+#[test]
+fn compare_user_gets_totals_and_deltas() {
+    let config = Config::parse_from(["whoami", "--compare-user", "hubot"]);
+    let stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &config, now()).unwrap();
+    let compare = stats.compare.unwrap();
+    assert_eq!(compare.username, "hubot");
+    assert_eq!(compare.total_stars, stats.total_stars);
+    assert_eq!((compare.stars_delta, compare.commits_delta, compare.prs_delta), (0, Some(0), 0));
+}
+
+#[test]
+fn extra_fields_land_in_extra() {
+    let config = Config::parse_from(["whoami", "--extra-field", "location"]);
+    let stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &config, now()).unwrap();
+    assert_eq!(stats.extra["location"], "Berlin");
+}
+
+#[test]
+fn extra_fields_outside_the_allowlist_are_rejected() {
+    let config = Config::parse_from(["whoami", "--extra-field", "repositories { totalCount }"]);
+    let err = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &config, now()).unwrap_err();
+    assert!(err.to_string().starts_with("unsupported --extra-field"), "{}", err);
+}
+
+#[test]
+fn calendar_days_are_summed_by_weekday() {
+    let stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &Config::default(), now()).unwrap();
+    let distribution: Vec<(String, u64)> = stats
+        .weekday_distribution
+        .into_iter()
+        .map(|day| (day.weekday, day.contributions))
+        .collect();
+    let expected = [("Mon", 10), ("Tue", 0), ("Wed", 0), ("Thu", 0), ("Fri", 0), ("Sat", 0), ("Sun", 6)];
+    assert_eq!(distribution, expected.map(|(day, n)| (day.to_string(), n)));
+}
+
+#[test]
+fn the_contribution_grid_shades_days_against_the_busiest() {
+    let stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &Config::default(), now()).unwrap();
+    // Two weeks from Sunday the 25th: 4, 7, 0, then 2 and 3 the next Sunday and Monday.
+    assert_eq!(stats.contribution_grid, "▓▒\n█▒\n·\n\n\n\n");
+
+    let stats = gather_stats(&FixtureTransport::new("graphql_down"), "octocat", &Config::default(), now()).unwrap();
+    assert_eq!(stats.contribution_grid, "");
+}
+
+#[test]
+fn influence_score_decays_stale_repos_and_counts_forks() {
+    let stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &Config::default(), now()).unwrap();
+    // 1840 + 17 + 0 fresh, plus (402 + 2 × 10 forks) pushed one half-life ago.
+    assert_eq!(stats.influence_score, Some(1857.0 + 211.0));
+}
+
+#[test]
+fn default_branch_mode_subtracts_excluded_authors() {
+    let commits = |args: &[&str]| {
+        let config = Config::parse_from(args);
+        gather_stats(&FixtureTransport::new("many_languages"), "octocat", &config, now()).unwrap().total_commits_this_year
+    };
+    assert_eq!(commits(&["whoami", "--commit-mode", "default-branch"]), Some(150));
+    assert_eq!(
+        commits(&["whoami", "--commit-mode", "default-branch", "--exclude-authors", "bot@example.com,dependabot[bot]"]),
+        Some(130)
+    );
+
+    let http = RecordingTransport::new("many_languages");
+    let config = Config::parse_from(["whoami", "--commit-mode", "default-branch", "--exclude-authors", "dependabot[bot],octocat-alt,ghost[bot]"]);
+    gather_stats(&http, "octocat", &config, now()).unwrap();
+    let variables = http.variables.lock().unwrap();
+    let query = variables.iter().find(|variables| variables.get("excluded0").is_some()).unwrap();
+    assert_eq!(query["excluded0"], serde_json::json!({ "emails": ["49699333+dependabot[bot]@users.noreply.github.com"] }));
+    assert_eq!(query["excluded1"], serde_json::json!({ "id": "MDQ6VXNlcjU4MzIzMQ==" }));
+    assert!(query.get("excluded2").is_none(), "an unknown bot isn't excluded");
+}
+
+#[test]
+fn unknown_excluded_logins_are_skipped() {
+    // GitHub answers `repositoryOwner` for a login nobody has with a null owner and no errors.
+    let http = RecordingTransport::new("many_languages");
+    let config = Config::parse_from(["whoami", "--commit-mode", "default-branch", "--exclude-authors", "no-such-user,octocat-alt"]);
+    let stats = gather_stats(&http, "octocat", &config, now()).unwrap();
+    assert!(!stats.degraded, "fell back to REST");
+    let variables = http.variables.lock().unwrap();
+    let query = variables.iter().find(|variables| variables.get("excluded0").is_some()).unwrap();
+    assert_eq!(query["excluded0"], serde_json::json!({ "id": "MDQ6VXNlcjU4MzIzMQ==" }));
+    assert!(query.get("excluded1").is_none(), "an unknown login isn't excluded");
+}
+
+/// Answers `DefaultBranchCommits` with a first page pointing at a second one.
+struct PagedCommitsTransport {
+    inner: FixtureTransport,
+}
+
+impl Transport for PagedCommitsTransport {
+    fn get(&self, url: &str) -> Result<Response, Box<dyn std::error::Error>> {
+        self.inner.get(url)
+    }
+
+    fn post_json(&self, url: &str, body: &serde_json::Value) -> Result<Response, Box<dyn std::error::Error>> {
+        if !body["query"].as_str().unwrap_or_default().contains("query DefaultBranchCommits") {
+            return self.inner.post_json(url, body);
+        }
+        let page = match body["variables"]["cursor"].as_str() {
+            None => r#"{ "nodes": [{ "defaultBranchRef": { "target": { "history": { "totalCount": 7 }, "excluded0": { "totalCount": 2 }, "excluded1": { "totalCount": 2 } } } }],
+                         "pageInfo": { "hasNextPage": true, "endCursor": "page2" } }"#,
+            Some("page2") => r#"{ "nodes": [{ "defaultBranchRef": { "target": { "history": { "totalCount": 4 }, "excluded0": { "totalCount": 1 } } } }],
+                                 "pageInfo": { "hasNextPage": false, "endCursor": "page2" } }"#,
+            Some(cursor) => panic!("unexpected cursor {}", cursor),
+        };
+        let body = format!(r#"{{ "data": {{ "user": {{ "repositories": {} }} }} }}"#, page);
+        Ok(Response { status: 200, headers: Default::default(), body })
+    }
+}
+
+#[test]
+fn default_branch_mode_follows_every_page_and_dedupes_authors() {
+    let http = PagedCommitsTransport { inner: FixtureTransport::new("many_languages") };
+    let config = Config::parse_from(["whoami", "--commit-mode", "default-branch", "--exclude-authors", "bot@example.com,bot@example.com"]);
+    let stats = gather_stats(&http, "octocat", &config, now()).unwrap();
+    // Subtracting the repeated email twice would take off its `excluded1` count as well.
+    assert_eq!(stats.total_commits_this_year, Some(7 - 2 + 4 - 1));
+}
+
+#[test]
+fn only_languages_skips_the_graphql_query() {
+    let config = Config::parse_from(["whoami", "--only", "languages"]);
+    let stats = gather_stats(&FixtureTransport::new("graphql_down"), "octocat", &config, now()).unwrap();
+    assert!(!stats.degraded, "GraphQL was queried");
+    assert!(!stats.languages.is_empty());
+
+    let context = whoami::render::build_context(&stats, &config).into_json();
+    assert_eq!((context["has_languages"].as_bool(), context["has_stars"].as_bool()), (Some(true), Some(false)));
+    assert!(context.get("total_stars").is_none());
+    assert!(context.get("languages").is_some());
+}
+
+#[test]
+fn repositories_are_followed_past_the_first_page() {
+    let stats = gather_stats(&FixtureTransport::new("many_repos"), "octocat", &Config::default(), now()).unwrap();
+    assert_eq!(stats.total_stars, 9);
+    assert_eq!(whoami::render::human_bytes(stats.total_disk_usage), "493 MB");
+}
+
+#[test]
+fn top_repos_rank_every_page_by_stars() {
+    let top = |args: &[&str]| -> Vec<String> {
+        let config = Config::parse_from(args);
+        let stats = gather_stats(&FixtureTransport::new("many_repos"), "octocat", &config, now()).unwrap();
+        stats.top_repos.into_iter().map(|repo| repo.name).collect()
+    };
+    assert_eq!(top(&["whoami", "--top-repos", "2"]), ["site", "cli"]);
+    assert_eq!(top(&["whoami"]), ["site", "cli", "dots"]);
+
+    let stats = gather_stats(&FixtureTransport::new("many_repos"), "octocat", &Config::default(), now()).unwrap();
+    let languages: Vec<(Option<&str>, Option<&str>)> =
+        stats.top_repos.iter().map(|repo| (repo.language.as_deref(), repo.language_color.as_deref())).collect();
+    assert_eq!(languages, [(Some("TypeScript"), Some("#3178c6")), (Some("Rust"), Some("#dea584")), (None, None)]);
+}
+
+#[test]
+fn external_contributions_are_opt_in() {
+    let http = FixtureTransport::new("many_languages");
+    let stats = gather_stats(&http, "octocat", &Config::default(), now()).unwrap();
+    assert_eq!((stats.external_prs, stats.external_issues), (None, None));
+
+    let config = Config::parse_from(["whoami", "--external-contributions"]);
+    let stats = gather_stats(&http, "octocat", &config, now()).unwrap();
+    assert_eq!((stats.external_prs, stats.external_issues), (Some(41), Some(6)));
+}
+
+#[test]
+fn contributed_languages_are_a_separate_chart() {
+    let http = FixtureTransport::new("many_languages");
+    let stats = gather_stats(&http, "octocat", &Config::default(), now()).unwrap();
+    assert!(stats.contributed_languages.is_empty());
+
+    let config = Config::parse_from(["whoami", "--contributed-languages"]);
+    let stats = gather_stats(&http, "octocat", &config, now()).unwrap();
+    assert_eq!(stats.contributed_languages, [("Go".to_string(), 60.0), ("Rust".to_string(), 20.0), ("Shell".to_string(), 20.0)]);
+    assert_eq!(stats.languages[0].0, "Rust");
+
+    let context = whoami::render::build_context(&stats, &config).into_json();
+    assert_eq!(context["contributed_languages"][0]["bar"], "▓▓▓▓▓▓░░░░");
+}
+
+#[test]
+fn organizations_list_public_memberships() {
+    let stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &Config::default(), now()).unwrap();
+    let logins: Vec<&str> = stats.organizations.iter().map(|org| org.login.as_str()).collect();
+    assert_eq!(logins, ["rust-lang", "bevyengine"]);
+    assert!(stats.organizations[0].avatar_url.starts_with("https://avatars.githubusercontent.com/"));
+
+    let stats = gather_stats(&FixtureTransport::new("single_repo"), "octocat", &Config::default(), now()).unwrap();
+    assert!(stats.organizations.is_empty());
+}
+
+#[test]
+fn account_age_is_dated_at_the_utc_offset() {
+    let config = Config::parse_from(["whoami", "--utc-offset", "+02:00"]);
+    let stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &config, now()).unwrap();
+    let context = whoami::render::build_context(&stats, &config).into_json();
+    assert_eq!(context["account_created"], "2015-03-11");
+    assert_eq!(context["account_age_years"], 10.2);
+    assert_eq!(context["account_age_days"], 3735);
+
+    let stats = gather_stats(&FixtureTransport::new("graphql_down"), "octocat", &config, now()).unwrap();
+    let context = whoami::render::build_context(&stats, &config).into_json();
+    assert!(context["account_created"].is_null() && context["account_age_years"].is_null());
+}
+
+#[test]
+fn all_time_commits_come_from_aliased_year_queries() {
+    let config = Config::parse_from(["whoami", "--all-time", "--count-restricted", "never", "--min-year-commits", "10"]);
+    let stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &config, now()).unwrap();
+    let all_time = stats.all_time.unwrap();
+    let years: Vec<i32> = all_time.years.iter().map(|year| year.year).collect();
+    assert_eq!(years, (2015..=2025).collect::<Vec<_>>());
+    assert_eq!(all_time.total_commits, 3258);
+    assert_eq!(all_time.years_active.len(), 9);
+}
+
+#[test]
+fn commits_vs_average_compares_this_year_with_the_earlier_ones() {
+    let commits_vs_average = |args: &[&str]| {
+        let config = Config::parse_from(args);
+        let stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &config, now()).unwrap();
+        whoami::render::build_context(&stats, &config).into_json()["commits_vs_average"].clone()
+    };
+    // 300 commits in 2025 against 2958 over the ten years before.
+    assert_eq!(commits_vs_average(&["whoami", "--all-time", "--count-restricted", "never"]), 1);
+    // Leaving out the two inactive years raises the average to 369.25.
+    let args = ["whoami", "--all-time", "--count-restricted", "never", "--min-year-commits", "10", "--exclude-inactive-years"];
+    assert_eq!(commits_vs_average(&args), -19);
+    assert!(commits_vs_average(&["whoami"]).is_null());
+}
+
+#[test]
+fn star_distribution_summarizes_owned_repos() {
+    let config = Config::default();
+    let stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &config, now()).unwrap();
+    let context = whoami::render::build_context(&stats, &config).into_json();
+    // Stars of 1840, 402, 17 and 0.
+    assert_eq!(context["median_stars"], 209.5);
+    assert_eq!((context["repos_over_10_stars"].as_u64(), context["repos_over_100_stars"].as_u64()), (Some(3), Some(2)));
+    assert_eq!(context["repos_over_1000_stars"], 1);
+
+    let stats = gather_stats(&FixtureTransport::new("no_languages"), "octocat", &config, now()).unwrap();
+    let context = whoami::render::build_context(&stats, &config).into_json();
+    assert_eq!((context["median_stars"].as_u64(), context["repos_over_10_stars"].as_u64()), (Some(0), Some(0)));
+}
+
+#[test]
+fn skipped_fields_leave_the_query_and_the_context() {
+    let http = RecordingTransport::new("many_languages");
+    let config = Config::parse_from(["whoami", "--skip-field", "contribution-calendar,repositories", "--skip-field", "organizations"]);
+    let stats = gather_stats(&http, "octocat", &config, now()).unwrap();
+
+    let queries = http.queries.lock().unwrap();
+    let main = &queries[0];
+    assert!(!main.contains("contributionCalendar") && !main.contains("repositories(first") && !main.contains("organizations("));
+    assert!(!main.contains("$privacy") && !main.contains("$organizations"), "{}", main);
+    assert!(main.contains("repositoriesContributedTo(") && main.contains("pinnedItems("));
+
+    let context = whoami::render::build_context(&stats, &config).into_json();
+    for skipped in ["weekday_distribution", "total_stars", "top_repos", "median_stars", "organizations"] {
+        assert!(context.get(skipped).is_none(), "{} is still there", skipped);
+    }
+    assert!(context.get("contributed_to").is_some() && context.get("pinned_repos").is_some());
+}
+
+#[test]
+fn pinned_repos_keep_pin_order() {
+    let stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &Config::default(), now()).unwrap();
+    let pinned: Vec<(&str, Option<&str>)> =
+        stats.pinned_repos.iter().map(|repo| (repo.name.as_str(), repo.language_color.as_deref())).collect();
+    assert_eq!(pinned, [("whoami", Some("#dea584")), ("notes", None)]);
+
+    let stats = gather_stats(&FixtureTransport::new("single_repo"), "octocat", &Config::default(), now()).unwrap();
+    assert!(stats.pinned_repos.is_empty());
+}
+
+#[test]
+fn count_restricted_decides_on_private_contributions() {
+    let http = FixtureTransport::new("many_languages");
+    let commits = |mode: &str| {
+        let config = Config::parse_from(["whoami", "--count-restricted", mode]);
+        let stats = gather_stats(&http, "octocat", &config, now()).unwrap();
+        (stats.total_commits_this_year, whoami::render::commits_source_label(&stats, &config))
+    };
+    assert_eq!(commits("always"), (Some(1232), "commits in 2025 (including private)".to_string()));
+    assert_eq!(commits("never"), (Some(1187), "commits in 2025 (excluding restricted)".to_string()));
+    // The fixtures can't confirm the token's scopes, so `auto` leaves them out.
+    assert_eq!(commits("auto").0, Some(1187));
+
+    // With no restricted contributions reported, there's nothing to say either way.
+    let config = Config::parse_from(["whoami", "--count-restricted", "never"]);
+    let stats = gather_stats(&FixtureTransport::new("single_repo"), "octocat", &config, now()).unwrap();
+    assert_eq!(whoami::render::commits_source_label(&stats, &config), "commits in 2025");
+}
+
+#[test]
+fn as_of_windows_the_counts_to_that_day() {
+    let http = RecordingTransport::new("many_languages");
+    let config = Config::parse_from(["whoami", "--as-of", "2022-12-31", "--utc-offset", "+02:00"]);
+    let stats = gather_stats(&http, "octocat", &config, now()).unwrap();
+    assert_eq!(stats.generated_at, now());
+    let variables = http.variables.lock().unwrap();
+    assert_eq!((variables[0]["from"].as_str(), variables[0]["to"].as_str()), (Some("2022-01-01T00:00:00+02:00"), Some("2022-12-31T23:59:59+02:00")));
+
+    let context = whoami::render::build_context(&stats, &config).into_json();
+    assert_eq!(context["as_of_date"], "2022-12-31");
+    assert_eq!(context["window"], "in 2022");
+    let current: Vec<(&str, bool)> =
+        context["stats"].as_array().unwrap().iter().map(|stat| (stat["key"].as_str().unwrap(), stat["current"].as_bool().unwrap())).collect();
+    assert_eq!(current[..2], [("total_stars", true), ("total_commits_this_year", false)]);
+
+    let http = RecordingTransport::new("many_languages");
+    let config = Config::parse_from(["whoami", "--as-of", "2022-06-30"]);
+    gather_stats(&http, "octocat", &config, now()).unwrap();
+    let variables = http.variables.lock().unwrap();
+    assert_eq!((variables[0]["from"].as_str(), variables[0]["to"].as_str()), (Some("2022-01-01T00:00:00+00:00"), Some("2022-06-30T23:59:59+00:00")));
+
+    let config = Config::parse_from(["whoami", "--as-of", "2022-12-31", "--window", "rolling-365"]);
+    assert_eq!(whoami::render::build_context(&stats, &config).into_json()["window"], "in the 365 days to 2022-12-31");
+    assert!(Config::try_parse_from(["whoami", "--as-of", "end of 2022"]).is_err());
+}
+
+#[test]
+fn since_last_run_counts_from_the_last_history_entry() {
+    let path = std::env::temp_dir().join(format!("whoami-since-last-run-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let config = Config::parse_from(["whoami", "--since-last-run", "--history-file", path.to_str().unwrap()]);
+    let stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &config, now()).unwrap();
+    assert_eq!(stats.commits_since_last_run, None);
+    assert!(whoami::render::build_context(&stats, &config).get("commits_since_last_run").is_none());
+
+    let mut previous = stats.clone();
+    previous.generated_at = Utc.with_ymd_and_hms(2025, 5, 25, 8, 30, 0).unwrap();
+    whoami::history::append_history(&path, &previous).unwrap();
+    let http = RecordingTransport::new("many_languages");
+    let stats = gather_stats(&http, "octocat", &config, now()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    // Restricted contributions count here whenever they do in the yearly total.
+    assert_eq!(stats.commits_since_last_run, Some(23 + 4));
+    let variables = http.variables.lock().unwrap();
+    let between = variables.iter().find(|variables| variables.get("from").is_some_and(|from| from == "2025-05-25T08:30:00+00:00")).unwrap();
+    assert_eq!(between["to"], "2025-06-01T12:00:00+00:00");
+    assert_eq!(whoami::render::build_context(&stats, &config).into_json()["commits_since_last_run"], "27");
+
+    assert!(Config::try_parse_from(["whoami", "--since-last-run"]).is_err());
+}
+
+/// Answers GraphQL queries about `showcase` from `many_languages`, the rest from
+/// `no_languages`.
+struct ShowcaseTransport {
+    empty: FixtureTransport,
+    showcase: FixtureTransport,
+}
+
+impl Transport for ShowcaseTransport {
+    fn get(&self, url: &str) -> Result<Response, Box<dyn std::error::Error>> {
+        self.empty.get(url)
+    }
+
+    fn post_json(&self, url: &str, body: &serde_json::Value) -> Result<Response, Box<dyn std::error::Error>> {
+        match body["variables"]["username"].as_str() {
+            Some("showcase") => self.showcase.post_json(url, body),
+            _ => self.empty.post_json(url, body),
+        }
+    }
+}
+
+#[test]
+fn an_empty_user_falls_back_to_the_fallback_user() {
+    let http = ShowcaseTransport { empty: FixtureTransport::new("no_languages"), showcase: FixtureTransport::new("many_languages") };
+    let stats = gather_stats(&http, "octocat", &Config::default(), now()).unwrap();
+    assert!(stats.is_empty() && !stats.fallback_used);
+
+    let config = Config::parse_from(["whoami", "--fallback-user", "showcase"]);
+    let stats = gather_stats(&http, "octocat", &config, now()).unwrap();
+    assert_eq!((stats.username.as_str(), stats.total_prs, stats.fallback_used), ("showcase", 312, true));
+    assert_eq!(whoami::render::build_context(&stats, &config).into_json()["fallback_used"], true);
+
+    let stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &config, now()).unwrap();
+    assert_eq!((stats.username.as_str(), stats.fallback_used), ("octocat", false));
+}
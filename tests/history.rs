@@ -0,0 +1,146 @@
+mod common;
+
+use chrono::prelude::*;
+use clap::Parser;
+use common::FixtureTransport;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use whoami::config::Config;
+use whoami::error::Error;
+use whoami::history::{HistoryEntry, append_history, check_regression, read_history, trend};
+use whoami::stats::gather_stats;
+
+fn history_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("whoami-{}-{}.jsonl", name, std::process::id()))
+}
+
+#[test]
+fn history_appends_one_line_per_run() {
+    let path = history_path("append");
+    let _ = fs::remove_file(&path);
+
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+    let mut stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &Config::default(), now).unwrap();
+    append_history(&path, &stats).unwrap();
+    OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{\"truncated\n").unwrap();
+    stats.generated_at += chrono::Duration::days(7);
+    stats.total_stars += 12;
+    append_history(&path, &stats).unwrap();
+
+    let history = read_history(&path);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[1].timestamp, stats.generated_at);
+    assert_eq!(history[1].total_stars - history[0].total_stars, 12);
+    let top: Vec<&str> = history[0].top_languages.iter().map(|(lang, _)| lang.as_str()).collect();
+    assert_eq!(top, ["Rust", "TypeScript", "Jupyter Notebook"]);
+}
+
+#[test]
+fn trend_compares_with_the_last_recorded_run() {
+    let path = history_path("trend");
+    let _ = fs::remove_file(&path);
+
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+    let mut stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &Config::default(), now).unwrap();
+    assert!(trend(&read_history(&path), &stats).is_none());
+    for _ in 0..8 {
+        append_history(&path, &stats).unwrap();
+        stats.total_stars += 4;
+    }
+    stats.total_commits_this_year = None;
+
+    let trend = trend(&read_history(&path), &stats).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(trend.stars_delta, 4);
+    assert_eq!(trend.commits_delta, None);
+    assert_eq!(trend.stars_history, [2267, 2271, 2275, 2279, 2283, 2287, 2291]);
+    assert_eq!(trend.commits_history.len(), 7);
+}
+
+#[test]
+fn fail_on_regression_catches_a_plummeting_stat() {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+    let config = Config::parse_from(["whoami", "--history-file", "h.jsonl", "--fail-on-regression"]);
+    let mut stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &config, now).unwrap();
+    let history = [HistoryEntry::from_stats(&stats)];
+    assert!(check_regression(&[], &stats, &config).is_ok());
+
+    stats.generated_at += chrono::Duration::days(1);
+    stats.total_prs = 0;
+    stats.total_stars = stats.total_stars * 3 / 5;
+    assert!(check_regression(&history, &stats, &config).is_ok(), "prs aren't guarded, and 40% down is within 50%");
+    stats.total_stars = 0;
+    let err = check_regression(&history, &stats, &config).unwrap_err();
+    assert!(matches!(&err, Error::Regression(message) if message.starts_with("stars fell from 2259 to 0 (100% down)")), "{}", err);
+
+    let config = Config::parse_from(["whoami", "--history-file", "h.jsonl", "--regression-guard", "commits", "--regression-threshold", "10"]);
+    assert!(check_regression(&history, &stats, &config).is_ok());
+    stats.total_commits_this_year = stats.total_commits_this_year.map(|commits| commits / 2);
+    assert!(check_regression(&history, &stats, &config).is_err());
+    // A calendar year's commits start over on January 1st.
+    stats.generated_at = Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap();
+    assert!(check_regression(&history, &stats, &config).is_ok());
+
+    assert!(Config::try_parse_from(["whoami", "--fail-on-regression"]).is_err());
+    assert!(Config::try_parse_from(["whoami", "--regression-threshold", "150"]).is_err());
+}
+
+#[test]
+fn openmetrics_replays_the_history_with_timestamps() {
+    let path = history_path("openmetrics");
+    let _ = fs::remove_file(&path);
+
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+    let mut stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &Config::default(), now).unwrap();
+    append_history(&path, &stats).unwrap();
+    stats.generated_at += chrono::Duration::milliseconds(86_400_250);
+    stats.total_stars += 3;
+
+    let config = Config::parse_from(["whoami", "--format", "openmetrics", "--history-file", path.to_str().unwrap()]);
+    let tera = tera::Tera::default();
+    let rendered = String::from_utf8(whoami::render::render_output(&tera, &stats, &config).unwrap()).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let stars: Vec<&str> = rendered.lines().filter(|line| line.starts_with("github_stats_stars{")).collect();
+    assert_eq!(stars, ["github_stats_stars{user=\"octocat\"} 2259 1748779200.000", "github_stats_stars{user=\"octocat\"} 2262 1748865600.250"]);
+    let rust = rendered.lines().filter(|line| line.contains("lang=\"Rust\"")).count();
+    assert_eq!(rust, 2);
+    assert!(rendered.ends_with("# EOF\n"));
+}
+
+#[test]
+fn languages_trend_against_the_last_recorded_run() {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+    let stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &Config::default(), now).unwrap();
+    let names: Vec<&str> = stats.languages.iter().map(|(lang, _)| lang.as_str()).collect();
+    assert_eq!(names[..4], ["Rust", "TypeScript", "Jupyter Notebook", "Python"]);
+
+    let mut previous = stats.clone();
+    previous.languages = vec![
+        ("Rust".to_string(), stats.languages[0].1 - 2.0),
+        ("TypeScript".to_string(), stats.languages[1].1 + 0.3),
+        ("Python".to_string(), stats.languages[3].1 + 5.0),
+    ];
+    let mut history = vec![HistoryEntry::from_stats(&previous)];
+    let trends = |history: &[HistoryEntry]| -> Vec<(String, Option<String>)> {
+        let mut stats = stats.clone();
+        stats.trend = trend(history, &stats);
+        let context = whoami::render::build_context(&stats, &Config::default()).into_json();
+        let languages = context["languages"].as_array().unwrap().iter().take(5);
+        languages.map(|lang| (lang["name"].as_str().unwrap().trim_end().to_string(), lang["trend"].as_str().map(String::from))).collect()
+    };
+    let expected = |third: &str| {
+        [("Rust", Some("up")), ("TypeScript", Some("flat")), ("Jupyter", Some(third)), ("Python", Some("down"))]
+            .map(|(name, trend)| (name.to_string(), trend.map(String::from)))
+    };
+    assert_eq!(trends(&history)[..4], expected("new"));
+    assert_eq!(trends(&history)[4].1, None, "below the top three, the history can't tell");
+    // In the top three again after dropping out of it.
+    let mut older = history[0].clone();
+    older.top_languages[1] = ("Jupyter Notebook".to_string(), 12.0);
+    history.insert(0, older);
+    assert_eq!(trends(&history)[..4], expected("up"));
+
+    assert!(trends(&[]).iter().all(|(_, trend)| trend.is_none()));
+}
@@ -0,0 +1,310 @@
+mod common;
+
+use chrono::prelude::*;
+use common::FixtureTransport;
+use clap::{CommandFactory, FromArgMatches, Parser};
+use std::collections::HashMap;
+use whoami::config::{Cli, Config};
+use whoami::http::Response;
+use whoami::languages::{NoLanguages, RepoLanguages, RepoSelection, calculate_language_stats, fetch_repo_languages, language_shares, language_timeline, merge_language_maps, sort_by_share};
+
+fn langs(entries: &[(&str, f64)]) -> Vec<(String, f64)> {
+    entries.iter().map(|(name, pct)| (name.to_string(), *pct)).collect()
+}
+
+#[test]
+fn equal_shares_sort_alphabetically() {
+    let mut languages = langs(&[("Zig", 25.0), ("Rust", 50.0), ("Ada", 25.0)]);
+    sort_by_share(&mut languages);
+    assert_eq!(languages, langs(&[("Rust", 50.0), ("Ada", 25.0), ("Zig", 25.0)]));
+}
+
+#[test]
+fn a_nan_share_sorts_without_panicking() {
+    let mut languages = langs(&[("Go", 10.0), ("Nix", f64::NAN), ("Rust", 50.0)]);
+    sort_by_share(&mut languages);
+    let names: Vec<&str> = languages.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["Nix", "Rust", "Go"]);
+}
+
+#[test]
+fn case_and_alias_variants_merge_into_one_language() {
+    let maps = vec![
+        HashMap::from([("Jupyter Notebook".to_string(), 100), ("Rust".to_string(), 30)]),
+        HashMap::from([("jupyter".to_string(), 50), ("rust".to_string(), 10), ("cpp".to_string(), 7)]),
+        HashMap::from([("C++".to_string(), 3)]),
+    ];
+    let merged: Vec<(String, u64)> = merge_language_maps(maps).into_iter().collect();
+    assert_eq!(
+        merged,
+        [("C++".to_string(), 10), ("Jupyter Notebook".to_string(), 150), ("Rust".to_string(), 40)]
+    );
+}
+
+#[test]
+fn timeline_credits_each_repo_to_its_creation_year() {
+    let repo = |year: Option<i32>, bytes: &[(&str, u64)]| RepoLanguages {
+        created_year: year,
+        pushed_at: None,
+        bytes: bytes.iter().map(|(lang, n)| (lang.to_string(), *n)).collect(),
+    };
+    let repos = vec![
+        repo(Some(2019), &[("Python", 500), ("Shell", 20)]),
+        repo(Some(2021), &[("Rust", 300)]),
+        repo(Some(2021), &[("Go", 200), ("rust", 50)]),
+        repo(Some(2022), &[("C", 100), ("Zig", 100)]),
+        repo(None, &[("Haskell", 9000)]),
+    ];
+    let timeline: Vec<(i32, String)> = language_timeline(&repos).into_iter().collect();
+    assert_eq!(timeline, [(2019, "Python".to_string()), (2021, "Rust".to_string())]);
+}
+
+#[test]
+fn recency_halflife_favours_recently_pushed_repos() {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+    let repo = |days_ago: i64, lang: &str| RepoLanguages {
+        created_year: None,
+        pushed_at: Some(now - chrono::Duration::days(days_ago)),
+        bytes: HashMap::from([(lang.to_string(), 1000)]),
+    };
+    let repos = vec![repo(0, "Rust"), repo(730, "Python")];
+
+    let even = language_shares(&repos, &Config::default(), now);
+    assert_eq!(even, langs(&[("Python", 50.0), ("Rust", 50.0)]));
+
+    let config = Config::parse_from(["whoami", "--lang-recency-halflife", "365"]);
+    let decayed = language_shares(&repos, &config, now);
+    assert_eq!(decayed, langs(&[("Rust", 80.0), ("Python", 20.0)]));
+}
+
+#[test]
+fn private_repos_only_count_when_opted_in() {
+    let http = FixtureTransport::new("single_repo");
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+    let names = |args: &[&str]| -> Vec<String> {
+        let config = Config::parse_from(args);
+        let stats = calculate_language_stats(&http, "octocat", &config, now).unwrap();
+        stats.languages.into_iter().map(|(name, _)| name).collect()
+    };
+    assert_eq!(names(&["whoami"]), ["Kotlin", "Java"]);
+    assert_eq!(names(&["whoami", "--include-private-langs"]), ["COBOL", "Kotlin", "Java"]);
+    assert_eq!(names(&["whoami", "--visibility", "private"]), ["COBOL", "Kotlin", "Java"]);
+}
+
+#[test]
+fn failed_language_fetches_warn_or_fail_under_strict() {
+    let http = FixtureTransport::new("single_repo");
+    assert_eq!(fetch_repo_languages(&http, &Config::default()).unwrap().repos.len(), 2);
+
+    let config = Config::parse_from(["whoami", "--strict"]);
+    let err = fetch_repo_languages(&http, &config).unwrap_err();
+    assert_eq!(err.to_string(), "languages unavailable for octocat/takedown: HTTP 404: Not Found");
+}
+
+#[test]
+fn app_installations_list_the_repos_they_were_installed_on() {
+    let http = FixtureTransport::new("single_repo");
+    let config = Config::parse_from(["whoami", "--auth", "app", "--include-private-langs"]);
+    let fetched = fetch_repo_languages(&http, &config).unwrap();
+    assert_eq!(fetched.listed, 2);
+    let mut languages: Vec<&String> = fetched.repos.iter().flat_map(|repo| repo.bytes.keys()).collect();
+    languages.sort();
+    assert_eq!(languages, ["COBOL", "Java", "Kotlin"]);
+}
+
+#[test]
+fn min_lang_bytes_drops_languages_below_the_floor() {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+    let repos = vec![RepoLanguages {
+        created_year: None,
+        pushed_at: None,
+        bytes: HashMap::from([("Rust".to_string(), 3000), ("Shell".to_string(), 1000), ("Makefile".to_string(), 999)]),
+    }];
+    let config = Config::parse_from(["whoami", "--min-lang-bytes", "1000"]);
+    assert_eq!(language_shares(&repos, &config, now), langs(&[("Rust", 75.0), ("Shell", 25.0)]));
+}
+
+#[test]
+fn empty_charts_say_why() {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+    let stats = whoami::stats::gather_stats(&FixtureTransport::new("no_languages"), "octocat", &Config::default(), now).unwrap();
+    assert_eq!(stats.languages_empty_reason, Some(NoLanguages::NoRepos));
+    let stats = whoami::stats::gather_stats(&FixtureTransport::new("many_languages"), "octocat", &Config::default(), now).unwrap();
+    assert_eq!(stats.languages_empty_reason, None);
+
+    let repo = |bytes: u64| RepoLanguages {
+        created_year: None,
+        pushed_at: None,
+        bytes: HashMap::from([("Rust".to_string(), bytes)]),
+    };
+    let reason = |repos: Vec<RepoLanguages>, filtered: usize, args: &[&str]| {
+        let selection = RepoSelection { listed: repos.len() + filtered, repos, filtered };
+        selection.no_languages(&Config::parse_from(args), now)
+    };
+    assert_eq!(reason(Vec::new(), 3, &["whoami"]), Some(NoLanguages::AllFiltered));
+    assert_eq!(reason(vec![repo(0)], 0, &["whoami"]), Some(NoLanguages::NoCode));
+    assert_eq!(reason(vec![repo(500)], 2, &["whoami", "--min-lang-bytes", "1000"]), Some(NoLanguages::BelowMinBytes));
+    assert_eq!(reason(vec![repo(500)], 2, &["whoami"]), None);
+}
+
+#[test]
+fn repo_count_charts_primary_languages() {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+    let config = Config::parse_from(["whoami", "--lang-metric", "repo-count"]);
+    let rounded = |languages: Vec<(String, f64)>| -> Vec<(String, f64)> {
+        languages.into_iter().map(|(name, pct)| (name, (pct * 100.0).round() / 100.0)).collect()
+    };
+
+    let stats = whoami::stats::gather_stats(&FixtureTransport::new("many_languages"), "octocat", &config, now).unwrap();
+    assert_eq!(rounded(stats.languages), langs(&[("Rust", 66.67), ("Go", 33.33)]));
+
+    // The REST fallback reads the listing's `language`, skipping the fork.
+    let stats = whoami::stats::gather_stats(&FixtureTransport::new("graphql_down"), "octocat", &config, now).unwrap();
+    assert_eq!(rounded(stats.languages), langs(&[("Ruby", 66.67), ("Shell", 33.33)]));
+
+    let stats = whoami::stats::gather_stats(&FixtureTransport::new("no_languages"), "octocat", &config, now).unwrap();
+    assert_eq!(stats.languages_empty_reason, Some(NoLanguages::NoRepos));
+}
+
+#[test]
+fn github_error_objects_are_reported_by_message() {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+    let config = Config::parse_from(["whoami", "--only", "languages"]);
+    let err = whoami::stats::gather_stats(&FixtureTransport::new("bad_credentials"), "octocat", &config, now).unwrap_err();
+    assert_eq!(err.to_string(), "GitHub API error: Bad credentials");
+
+    let response = Response {
+        status: 401,
+        headers: HashMap::new(),
+        body: r#"{"message":"Bad credentials","documentation_url":"https://docs.github.com/rest"}"#.to_string(),
+    };
+    let err = response.rest_json::<Vec<serde_json::Value>>().unwrap_err();
+    assert!(matches!(err, whoami::error::Error::Auth(_)), "{:?}", err);
+    assert_eq!(err.to_string(), "GitHub rejected the token: HTTP 401: Bad credentials");
+
+    let languages = Response { status: 200, headers: HashMap::new(), body: r#"{"message": 12}"#.to_string() };
+    assert_eq!(languages.github_error(), None);
+}
+
+#[test]
+fn a_repos_file_replaces_the_listing() {
+    let repos_file = common::fixture_dir("listed_repos").join("repos.txt");
+    let config = Config::parse_from(["whoami", "--repos-file", repos_file.to_str().unwrap()]);
+    assert_eq!(whoami::languages::listed_repos(&config).unwrap(), ["octocat/engine", "bevyengine/bevy", "octocat/gone"]);
+
+    // `octocat/gone` 404s and is left out; the others count whoever owns them.
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+    let stats = whoami::stats::gather_stats(&FixtureTransport::new("listed_repos"), "octocat", &config, now).unwrap();
+    assert_eq!(stats.languages, langs(&[("Rust", 90.0), ("WGSL", 10.0)]));
+    assert_eq!(stats.total_stars, 40840);
+
+    let config = Config::parse_from(["whoami", "--repo", "octocat/engine,octocat"]);
+    let err = whoami::languages::listed_repos(&config).unwrap_err();
+    assert_eq!(err.to_string(), r#"expected a repo as owner/repo, got "octocat""#);
+}
+
+#[test]
+fn lang_from_contributions_charts_the_repos_committed_to() {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+    let languages = |args: &[&str]| -> Vec<(String, f64)> {
+        let config = Config::parse_from(args);
+        let stats = whoami::stats::gather_stats(&FixtureTransport::new("many_languages"), "octocat", &config, now).unwrap();
+        stats.languages.into_iter().map(|(name, pct)| (name, (pct * 100.0).round() / 100.0)).collect()
+    };
+
+    // The private repo stays out unless asked for, like an owned one.
+    assert_eq!(languages(&["whoami", "--lang-from", "contributions"]), langs(&[("Rust", 86.67), ("Python", 6.67), ("WGSL", 6.67)]));
+    let private = ["whoami", "--lang-from", "contributions", "--include-private-langs"];
+    assert_eq!(languages(&private), langs(&[("Rust", 65.0), ("Go", 25.0), ("Python", 5.0), ("WGSL", 5.0)]));
+    let repo_count = ["whoami", "--lang-from", "contributions", "--include-private-langs", "--lang-metric", "repo-count"];
+    assert_eq!(languages(&repo_count), langs(&[("Rust", 66.67), ("Go", 33.33)]));
+}
+
+/// Answers 401 to every REST request matching `rejects`, as GitHub does once a token expires.
+struct ExpiringTransport {
+    inner: FixtureTransport,
+    rejects: fn(&str) -> bool,
+}
+
+impl whoami::http::Transport for ExpiringTransport {
+    fn get(&self, url: &str) -> Result<Response, Box<dyn std::error::Error>> {
+        if (self.rejects)(url) {
+            let body = r#"{"message":"Bad credentials","documentation_url":"https://docs.github.com/rest"}"#.to_string();
+            return Ok(Response { status: 401, headers: HashMap::new(), body });
+        }
+        self.inner.get(url)
+    }
+
+    fn post_json(&self, url: &str, body: &serde_json::Value) -> Result<Response, Box<dyn std::error::Error>> {
+        self.inner.post_json(url, body)
+    }
+}
+
+#[test]
+fn a_rejected_token_fails_the_language_chart_instead_of_blanking_it() {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+    let rejects: [fn(&str) -> bool; 2] = [|url| url.contains("/user/repos"), |url| url.ends_with("/languages")];
+    for rejects in rejects {
+        let http = ExpiringTransport { inner: FixtureTransport::new("many_languages"), rejects };
+        let err = whoami::stats::gather_stats(&http, "octocat", &Config::default(), now).unwrap_err();
+        assert!(matches!(err, whoami::error::Error::Auth(_)), "{:?}", err);
+    }
+}
+
+#[test]
+fn repos_can_be_excluded_by_name_and_pattern() {
+    let http = FixtureTransport::new("many_languages");
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+    let names = |args: &[&str]| -> Vec<String> {
+        let stats = calculate_language_stats(&http, "octocat", &Config::parse_from(args), now).unwrap();
+        stats.languages.into_iter().map(|(name, _)| name).collect()
+    };
+    let args = ["whoami", "--exclude-repo", "TOOLS", "--exclude-repo-pattern", "^octocat/(dash|note)"];
+    assert_eq!(names(&args), ["Rust", "C++", "GLSL", "CMake"]);
+
+    assert!(Config::try_parse_from(["whoami", "--exclude-repo-pattern", "legacy-*("]).is_err());
+}
+
+#[test]
+fn strict_languages_sum_needs_the_other_bucket_past_eight_languages() {
+    let http = FixtureTransport::new("many_languages");
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+    let err = whoami::stats::gather_stats(&http, "octocat", &Config::parse_from(["whoami", "--strict-languages-sum"]), now).unwrap_err();
+    assert!(err.to_string().starts_with("the charted language percentages add up to 9"), "{}", err);
+
+    let config = Config::parse_from(["whoami", "--strict-languages-sum", "--lang-other"]);
+    let stats = whoami::stats::gather_stats(&http, "octocat", &config, now).unwrap();
+    assert_eq!(stats.languages.len(), 9);
+    assert_eq!(stats.languages.last().unwrap().0, "Other");
+    let sum: f64 = stats.languages.iter().map(|(_, share)| share).sum();
+    assert!((sum - 100.0).abs() < 1e-9, "{}", sum);
+}
+
+#[test]
+fn language_overrides_adjust_a_repos_bytes_before_the_shares() {
+    let path = std::env::temp_dir().join(format!("whoami-overrides-{}.toml", std::process::id()));
+    // octocat/notebooks is 154022 bytes of Jupyter Notebook and 21877 of Python.
+    std::fs::write(&path, "[language-overrides.NOTEBOOKS]\n\"Jupyter Notebook\" = -154022\nPython = 3000\n").unwrap();
+    let args = ["whoami", "--config", path.to_str().unwrap(), "--exclude-repo", "engine,dashboard,tools"];
+    let matches = Cli::command().get_matches_from(args);
+    let config = Cli::from_arg_matches(&matches).unwrap().config.with_file(&matches).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+    let stats = calculate_language_stats(&FixtureTransport::new("many_languages"), "octocat", &config, now).unwrap();
+    assert_eq!(stats.languages, langs(&[("Python", 100.0)]));
+}
+
+#[test]
+fn estimated_lines_divide_each_languages_bytes_by_its_line_length() {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+    let mut config = Config::parse_from(["whoami", "--estimate-lines", "--exclude-repo", "engine,dashboard,tools"]);
+    // 154022 bytes of Jupyter Notebook at 120 a line, 21877 of Python at 32.
+    let stats = calculate_language_stats(&FixtureTransport::new("many_languages"), "octocat", &config, now).unwrap();
+    assert_eq!(stats.estimated_lines, Some(1967));
+
+    config.bytes_per_line.insert("Python".to_string(), 21877.0);
+    let stats = calculate_language_stats(&FixtureTransport::new("many_languages"), "octocat", &config, now).unwrap();
+    assert_eq!(stats.estimated_lines, Some(1285));
+    assert_eq!(calculate_language_stats(&FixtureTransport::new("many_languages"), "octocat", &Config::default(), now).unwrap().estimated_lines, None);
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use chrono::prelude::*;
+use common::FixtureTransport;
+use tera::Tera;
+use whoami::config::Config;
+use whoami::history::Trend;
+use whoami::lint::lint_template;
+use whoami::render::{CONTEXT_VARIABLES, build_context};
+use whoami::stats::{AllTimeStats, gather_stats};
+
+#[test]
+fn lint_reports_typos_but_not_locals() {
+    let mut tera = Tera::default();
+    tera.add_raw_template(
+        "card",
+        "{{ total_star }} {% for lang in languages %}{{ lang.name }}{{ loop.index }}{% endfor %}\
+         {% set shown = username | upper %}{{ shown }}{% if top_language is defined %}{{ extra.bio }}{% endif %}",
+    )
+    .unwrap();
+    let report = lint_template(&tera, "card").unwrap();
+    assert_eq!(report.undefined.into_iter().collect::<Vec<_>>(), ["total_star"]);
+    assert!(report.unused.contains("total_stars"));
+    for used in ["languages", "username", "top_language", "extra"] {
+        assert!(!report.unused.contains(used), "{} reported unused", used);
+    }
+}
+
+#[test]
+fn context_variables_cover_the_full_context() {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+    let config = Config::default();
+    let mut stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &config, now).unwrap();
+    stats.all_time = Some(AllTimeStats {
+        total_commits: 0,
+        years: Vec::new(),
+        restricted_commits: 0,
+        years_active: Vec::new(),
+        commits_vs_average: None,
+        language_timeline: Vec::new(),
+    });
+    stats.trend = Some(Trend {
+        since: now,
+        stars_delta: 0,
+        commits_delta: Some(0),
+        stars_history: Vec::new(),
+        commits_history: Vec::new(),
+        languages: Default::default(),
+    });
+    let context = build_context(&stats, &config).into_json();
+    for key in context.as_object().unwrap().keys() {
+        assert!(CONTEXT_VARIABLES.contains(&key.as_str()) || key.starts_with("has_"), "{} missing from CONTEXT_VARIABLES", key);
+    }
+}
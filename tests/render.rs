@@ -0,0 +1,351 @@
+mod common;
+
+use chrono::prelude::*;
+use clap::Parser;
+use common::FixtureTransport;
+use whoami::config::{BarDirection, BarScale, Config};
+use whoami::render::{bar_full_scale, build_context, format_percentage, load_templates, render_output, render_progress_bar, render_svg};
+use whoami::stats::gather_stats;
+
+fn bars(scale: BarScale) -> Vec<String> {
+    let languages = vec![("Rust".to_string(), 50.0), ("Go".to_string(), 25.0), ("C".to_string(), 5.0)];
+    let full_scale = bar_full_scale(scale, &languages);
+    languages.iter().map(|(_, pct)| render_progress_bar(*pct, full_scale, BarDirection::Ltr, None)).collect()
+}
+
+#[test]
+fn absolute_bars_fill_against_one_hundred_percent() {
+    assert_eq!(bars(BarScale::Absolute), ["▓▓▓▓▓░░░░░", "▓▓▓░░░░░░░", "▓░░░░░░░░░"]);
+}
+
+#[test]
+fn relative_bars_fill_against_the_top_language() {
+    assert_eq!(bars(BarScale::Relative), ["▓▓▓▓▓▓▓▓▓▓", "▓▓▓▓▓░░░░░", "▓░░░░░░░░░"]);
+}
+
+#[test]
+fn rtl_bars_fill_from_the_right() {
+    assert_eq!(render_progress_bar(30.0, 100.0, BarDirection::Ltr, None), "▓▓▓░░░░░░░");
+    assert_eq!(render_progress_bar(30.0, 100.0, BarDirection::Rtl, None), "░░░░░░░▓▓▓");
+}
+
+fn svg_with_hostile_strings(args: &[&str]) -> String {
+    svg_with_config(&Config::parse_from(args))
+}
+
+fn svg_with_config(config: &Config) -> String {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+    let mut stats = gather_stats(&FixtureTransport::new("single_repo"), "octocat", config, now).unwrap();
+    stats.username = "o'cat".to_string();
+    stats.languages = vec![(r#"A&B <"x">"#.to_string(), 100.0)];
+    let tera = load_templates(concat!(env!("CARGO_MANIFEST_DIR"), "/templates/**/*.tera"), config).unwrap();
+    render_svg(&tera, &stats, config).unwrap()
+}
+
+#[test]
+fn svg_values_are_escaped() {
+    let svg = svg_with_hostile_strings(&["whoami"]);
+    assert!(svg.contains("A&amp;B &lt;&quot;x&quot;&gt;"), "{}", svg);
+    assert!(svg.contains("o&#x27;cat"), "{}", svg);
+    assert!(!svg.contains("<\"x\">"), "{}", svg);
+}
+
+#[test]
+fn no_autoescape_inserts_values_verbatim() {
+    let svg = svg_with_hostile_strings(&["whoami", "--no-autoescape"]);
+    assert!(svg.contains(r#"A&B <"x">"#), "{}", svg);
+}
+
+#[test]
+fn percentages_follow_the_requested_precision() {
+    let formatted = |pct: f64| [0, 1, 2].map(|precision| format_percentage(pct, precision));
+    assert_eq!(formatted(47.456), ["47%", "47.5%", "47.46%"]);
+    assert_eq!(formatted(0.004), ["<1%", "<0.1%", "<0.01%"]);
+    assert_eq!(formatted(0.0), ["0%", "0.0%", "0.00%"]);
+}
+
+#[test]
+fn missing_stats_fall_back_through_or_zero_and_or_dash() {
+    let config = Config::default();
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+    let stats = gather_stats(&FixtureTransport::new("graphql_down"), "octocat", &config, now).unwrap();
+    let mut tera = load_templates(concat!(env!("CARGO_MANIFEST_DIR"), "/templates/**/*.tera"), &config).unwrap();
+    tera.add_raw_template(
+        "fallbacks",
+        "{{ total_commits_this_year | or_dash }} {{ total_commits_all_time | or_zero }} {{ total_stars | or_dash }}",
+    )
+    .unwrap();
+    let rendered = tera.render("fallbacks", &build_context(&stats, &config)).unwrap();
+    assert_eq!(rendered, "- 0 42");
+}
+
+#[test]
+fn sparkline_filter_scales_between_min_and_max() {
+    let mut tera = load_templates(concat!(env!("CARGO_MANIFEST_DIR"), "/templates/**/*.tera"), &Config::default()).unwrap();
+    tera.add_raw_template("spark", "{{ points | sparkline }} {{ flat | sparkline }}").unwrap();
+    let mut context = tera::Context::new();
+    context.insert("points", &[10, 17, 24, 31, 38, 45, 52, 59]);
+    context.insert("flat", &[3, 3]);
+    assert_eq!(tera.render("spark", &context).unwrap(), "▁▂▃▄▅▆▇█ ▁▁");
+}
+
+#[test]
+fn template_functions_compute_derived_values() {
+    let mut tera = load_templates(concat!(env!("CARGO_MANIFEST_DIR"), "/templates/**/*.tera"), &Config::default()).unwrap();
+    tera.add_raw_template(
+        "functions",
+        "{{ add(a=1, b=2) }} {{ div(a=7, b=2) }} {{ max(a=3, b=9) }} {{ percent_of(part=1, whole=4) }} \
+         {{ percent_of(part=1, whole=0) }} {{ color_for_language(name=\"Rust\") }} {{ color_for_language(name=\"Jupyter   \") }}",
+    )
+    .unwrap();
+    assert_eq!(tera.render("functions", &tera::Context::new()).unwrap(), "3 3.5 9 25 0 #dea584 #DA5B0B");
+
+    tera.add_raw_template("zero", "{{ div(a=1, b=0) }}").unwrap();
+    assert!(tera.render("zero", &tera::Context::new()).is_err());
+}
+
+#[test]
+fn human_bytes_picks_a_binary_unit() {
+    assert_eq!(whoami::render::human_bytes(512), "512 B");
+    assert_eq!(whoami::render::human_bytes(1536), "1.5 KB");
+    assert_eq!(whoami::render::human_bytes(450 * 1024 * 1024), "450 MB");
+}
+
+#[test]
+fn stacked_bar_widths_sum_to_the_length() {
+    let splits: [&[f64]; 4] = [&[33.3, 33.3, 33.4], &[99.0, 0.5, 0.5], &[12.5; 8], &[40.26, 17.09, 12.48, 9.1, 7.7, 5.2, 4.4, 3.1]];
+    for split in splits {
+        let languages: Vec<(String, f64)> = split.iter().enumerate().map(|(i, pct)| (format!("L{}", i), *pct)).collect();
+        for length in [1, 7, 10, 30, 100] {
+            let widths: Vec<usize> = whoami::render::stacked_bar(&languages, length).iter().map(|s| s.width).collect();
+            assert_eq!(widths.iter().sum::<usize>(), length, "{:?} over {}: {:?}", split, length, widths);
+        }
+    }
+    let languages = vec![("Rust".to_string(), 50.0), ("Go".to_string(), 30.0), ("C".to_string(), 20.0)];
+    let widths: Vec<usize> = whoami::render::stacked_bar(&languages, 7).iter().map(|s| s.width).collect();
+    assert_eq!(widths, [4, 2, 1]);
+}
+
+#[test]
+fn the_language_shapes_share_one_breakdown() {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+    let config = Config::parse_from(["whoami", "--stacked-bar-length", "20"]);
+    let stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &config, now).unwrap();
+    let context = build_context(&stats, &config).into_json();
+    let names = |shape: &str| -> Vec<String> {
+        context[shape].as_array().unwrap().iter().map(|lang| lang["name"].as_str().unwrap().trim_end().to_string()).collect()
+    };
+    assert!(!names("languages").is_empty());
+    assert_eq!(names("languages_stacked"), names("languages"));
+    assert_eq!(names("languages_ranked"), names("languages"));
+    assert_eq!(context["languages_stacked"], context["stacked_bar"]);
+    let widths: u64 = context["languages_stacked"].as_array().unwrap().iter().map(|segment| segment["width"].as_u64().unwrap()).sum();
+    assert_eq!(widths, 20);
+    let first = &context["languages_ranked"][0];
+    assert_eq!((first["rank"].as_u64(), &first["percentage"]), (Some(1), &context["languages"][0]["percentage"]));
+    assert!(first.get("bar").is_none());
+}
+
+#[test]
+fn ansi_color_wraps_only_the_filled_cells() {
+    assert_eq!(whoami::colors::nearest_ansi256("#ff0000"), Some(196));
+    assert_eq!(whoami::colors::nearest_ansi256("#808080"), Some(244));
+    assert_eq!(whoami::colors::nearest_ansi256("#dea584"), Some(180));
+    assert_eq!(render_progress_bar(30.0, 100.0, BarDirection::Ltr, Some(196)), "\x1b[38;5;196m▓▓▓\x1b[0m░░░░░░░");
+    assert_eq!(render_progress_bar(0.0, 100.0, BarDirection::Ltr, Some(196)), "░░░░░░░░░░");
+}
+
+#[test]
+fn text_output_takes_the_requested_bom_and_line_endings() {
+    let encode = |args: &[&str], bytes: &[u8]| whoami::render::encode_output(bytes.to_vec(), &Config::parse_from(args));
+    assert_eq!(encode(&["whoami"], b"a\r\nb\n"), b"a\nb\n");
+    assert_eq!(encode(&["whoami", "--line-endings", "crlf"], b"a\r\nb\n"), b"a\r\nb\r\n");
+    assert_eq!(encode(&["whoami", "--bom"], b"a\n"), b"\xef\xbb\xbfa\n");
+    assert_eq!(encode(&["whoami", "--format", "png", "--bom", "--line-endings", "crlf"], b"\x89PNG\n"), b"\x89PNG\n");
+}
+
+#[test]
+fn template_dirs_resolve_in_order_before_the_built_ins() {
+    let root = std::env::temp_dir().join(format!("whoami-template-dirs-{}", std::process::id()));
+    let (profile, shared) = (root.join("profile"), root.join("shared"));
+    std::fs::create_dir_all(shared.join("cards")).unwrap();
+    std::fs::create_dir_all(&profile).unwrap();
+    std::fs::write(profile.join("README.md.tera"), "profile {{ username }}").unwrap();
+    std::fs::write(profile.join("base.tera"), "[{% block body %}{% endblock %}]").unwrap();
+    std::fs::write(shared.join("README.md.tera"), "shared").unwrap();
+    std::fs::write(shared.join("cards/small.tera"), "{% extends \"base.tera\" %}{% block body %}small{% endblock %}").unwrap();
+
+    let config = Config::parse_from(["whoami", "--template-dir", profile.to_str().unwrap(), "--template-dir", shared.to_str().unwrap()]);
+    let tera = load_templates(concat!(env!("CARGO_MANIFEST_DIR"), "/templates/**/*.tera"), &config).unwrap();
+    let strict = Config::parse_from(["whoami", "--strict", "--template-dir", profile.to_str().unwrap(), "--template-dir", shared.to_str().unwrap()]);
+    let err = load_templates(concat!(env!("CARGO_MANIFEST_DIR"), "/templates/**/*.tera"), &strict).unwrap_err();
+    let expected = format!("{} is used as README.md.tera, not {}", profile.join("README.md.tera").display(), shared.join("README.md.tera").display());
+    assert_eq!(err.to_string(), format!("duplicate template names: {}", expected));
+    std::fs::remove_dir_all(&root).unwrap();
+    let mut context = tera::Context::new();
+    context.insert("username", "octocat");
+    assert_eq!(tera.render("README.md.tera", &context).unwrap(), "profile octocat");
+    assert_eq!(tera.render("cards/small.tera", &context).unwrap(), "[small]");
+    assert!(tera.get_template_names().any(|name| name == "stats.svg.tera"));
+
+    let config = Config::parse_from(["whoami", "--template-dir", "/nonexistent/templates"]);
+    assert!(load_templates(concat!(env!("CARGO_MANIFEST_DIR"), "/templates/**/*.tera"), &config).is_err());
+}
+
+#[test]
+fn a_template_file_renders_in_place_of_the_bundled_one() {
+    let path = std::env::temp_dir().join(format!("whoami-template-file-{}.svg.tera", std::process::id()));
+    std::fs::write(&path, "<text>{{ username }}</text>").unwrap();
+    let config = Config::parse_from(["whoami", "--format", "svg", "--template-file", path.to_str().unwrap()]);
+    let svg = svg_with_config(&config);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(svg, "<text>o&#x27;cat</text>");
+}
+
+#[test]
+fn html_and_json_template_files_render_as_their_formats() {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+    let mut stats = gather_stats(&FixtureTransport::new("single_repo"), "octocat", &Config::default(), now).unwrap();
+    stats.username = "o'cat".to_string();
+    let render = |extension: &str, template: &str| {
+        let path = std::env::temp_dir().join(format!("whoami-template-file-{}.{}.tera", std::process::id(), extension));
+        std::fs::write(&path, template).unwrap();
+        let config = Config::parse_from(["whoami", "--format", extension, "--template-file", path.to_str().unwrap()]);
+        let tera = load_templates(concat!(env!("CARGO_MANIFEST_DIR"), "/templates/**/*.tera"), &config).unwrap();
+        let rendered = render_output(&tera, &stats, &config).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        String::from_utf8(rendered).unwrap()
+    };
+    assert_eq!(render("html", "<p>{{ username }}</p>"), "<p>o&#x27;cat</p>");
+    assert_eq!(render("json", r#"{"stars": {{ total_stars }}}"#), r#"{"stars": 9}"#);
+}
+
+#[test]
+fn a_template_string_renders_unescaped_to_stdout() {
+    let config = Config::parse_from(["whoami", "--template-string", "⭐ {{ total_stars | or_dash }} {{ username }}"]);
+    assert_eq!(config.output_path(), std::path::Path::new("-"));
+    let svg = svg_with_config(&config);
+    assert_eq!(svg, "⭐ 9 o'cat");
+}
+
+#[test]
+fn stats_lists_the_headline_counts_in_order() {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+    let keys = |args: &[&str], case: &str| -> Vec<String> {
+        let config = Config::parse_from(args);
+        let stats = gather_stats(&FixtureTransport::new(case), "octocat", &config, now).unwrap();
+        let context = build_context(&stats, &config).into_json();
+        context["stats"].as_array().unwrap().iter().map(|stat| stat["key"].as_str().unwrap().to_string()).collect()
+    };
+    assert_eq!(keys(&["whoami"], "many_languages"), ["total_stars", "total_commits_this_year", "total_prs", "total_issues", "contributed_to"]);
+    // No commit counts over REST, and no PRs or issues outside `--only`.
+    assert_eq!(keys(&["whoami"], "graphql_down"), ["total_stars", "total_prs", "total_issues"]);
+    assert_eq!(keys(&["whoami", "--only", "stars,commits"], "many_languages"), ["total_stars", "total_commits_this_year"]);
+
+    let config = Config::default();
+    let stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &config, now).unwrap();
+    let context = build_context(&stats, &config).into_json();
+    assert_eq!(context["stats"][0], serde_json::json!({ "key": "total_stars", "label": "Stars", "value": "2.3k", "raw": 2259, "current": false }));
+    assert_eq!(context["stats"][1]["label"], "Commits in 2025 (including private)");
+}
+
+#[test]
+fn data_age_is_measured_when_rendering() {
+    let age = |seconds: i64| whoami::render::human_age(chrono::TimeDelta::seconds(seconds));
+    assert_eq!([age(59), age(60), age(7_199), age(86_400 * 21)], ["just now", "1 minute ago", "1 hour ago", "21 days ago"]);
+
+    let gathered = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+    let config = Config::parse_from(["whoami", "--now", "2025-06-04T13:00:00Z"]);
+    let stats = gather_stats(&FixtureTransport::new("single_repo"), "octocat", &config, gathered).unwrap();
+    let context = build_context(&stats, &config).into_json();
+    assert_eq!(context["generated_at"], "2025-06-01T12:00:00+00:00");
+    assert_eq!(context["data_age"], "3 days ago");
+}
+
+#[test]
+fn language_gauge_help_follows_the_lang_metric() {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+    let stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &Config::default(), now).unwrap();
+    let help = |args: &[&str]| {
+        let metrics = whoami::metrics::render_prometheus(&stats, &Config::parse_from(args));
+        metrics.lines().find(|line| line.starts_with("# HELP github_stats_language_percent")).unwrap().to_string()
+    };
+    assert_eq!(help(&["whoami"]), "# HELP github_stats_language_percent Share of code bytes per language.");
+    assert_eq!(
+        help(&["whoami", "--lang-metric", "repo-count"]),
+        "# HELP github_stats_language_percent Share of repos per language, counting each repo's primary language."
+    );
+    assert_eq!(
+        help(&["whoami", "--lang-recency-halflife", "90"]),
+        "# HELP github_stats_language_percent Share of code bytes per language, weighted toward recently pushed repos."
+    );
+}
+
+#[test]
+fn output_over_the_byte_budget_fails_or_sheds_languages() {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+    let stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &Config::default(), now).unwrap();
+    let render = |args: &[&str]| {
+        let config = Config::parse_from(args);
+        let tera = load_templates(concat!(env!("CARGO_MANIFEST_DIR"), "/templates/**/*.tera"), &config).unwrap();
+        whoami::render::render_within_budget(&tera, &stats, &config).map(|bytes| String::from_utf8(bytes).unwrap())
+    };
+    let bars = |markdown: &str| markdown.lines().filter(|line| line.contains('░')).count();
+    let full = render(&["whoami", "--format", "markdown"]).unwrap();
+
+    let budget = (full.len() - 1).to_string();
+    let err = render(&["whoami", "--format", "markdown", "--max-output-bytes", &budget]).unwrap_err();
+    assert_eq!(err.to_string(), format!("the output is {} bytes, over --max-output-bytes {}", full.len(), budget));
+
+    let fitted = render(&["whoami", "--format", "markdown", "--max-output-bytes", &budget, "--fit-output"]).unwrap();
+    assert_eq!(bars(&fitted), bars(&full) - 1);
+    assert!(render(&["whoami", "--format", "markdown", "--max-output-bytes", "10", "--fit-output"]).is_err());
+
+    // The smallest real language goes, and `Other` takes its share.
+    let mut stats = stats.clone();
+    stats.languages = vec![("Rust".to_string(), 70.0), ("Go".to_string(), 20.0), ("C".to_string(), 6.0), ("Other".to_string(), 4.0)];
+    let config = Config::parse_from(["whoami", "--format", "markdown", "--lang-other"]);
+    let tera = load_templates(concat!(env!("CARGO_MANIFEST_DIR"), "/templates/**/*.tera"), &config).unwrap();
+    let full = String::from_utf8(whoami::render::render_within_budget(&tera, &stats, &config).unwrap()).unwrap();
+    let budget = (full.len() - 1).to_string();
+    let config = Config::parse_from(["whoami", "--format", "markdown", "--lang-other", "--max-output-bytes", &budget, "--fit-output"]);
+    let fitted = String::from_utf8(whoami::render::render_within_budget(&tera, &stats, &config).unwrap()).unwrap();
+    assert!(!fitted.contains("\nC "), "{}", fitted);
+    assert!(fitted.contains("Other           ▓░░░░░░░░░ 10.00%"), "{}", fitted);
+}
+
+#[test]
+fn group_separates_thousands() {
+    assert_eq!([0, 999, 1_000, 1_234_567].map(whoami::render::group_digits), ["0", "999", "1,000", "1,234,567"]);
+}
+
+#[test]
+fn numbers_are_abbreviated_from_the_threshold_up() {
+    let abbreviate = |n: u64, args: &[&str]| whoami::render::abbreviate_number(n, &Config::parse_from(args));
+    assert_eq!([999, 1_000, 1_234_567].map(|n| abbreviate(n, &["whoami"])), ["999", "1.0k", "1.2M"]);
+    let raised = ["whoami", "--abbreviate-threshold", "10000"];
+    assert_eq!([9_999, 10_000].map(|n| abbreviate(n, &raised)), ["9999", "10.0k"]);
+    let grouped = ["whoami", "--abbreviate-threshold", "10000", "--group-digits"];
+    assert_eq!([999, 9_999].map(|n| abbreviate(n, &grouped)), ["999", "9,999"]);
+    assert_eq!([999_949, 999_950, 999_999].map(|n| abbreviate(n, &["whoami"])), ["999.9k", "1.0M", "1.0M"]);
+    let everything = ["whoami", "--abbreviate-threshold", "0"];
+    assert_eq!([0, 49, 50, 1_000].map(|n| abbreviate(n, &everything)), ["0", "49", "0.1k", "1.0k"]);
+}
+
+#[test]
+fn json_is_pretty_in_files_and_compact_on_stdout() {
+    let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+    let stats = gather_stats(&FixtureTransport::new("many_languages"), "octocat", &Config::default(), now).unwrap();
+    let json = |args: &[&str]| whoami::render::render_json(&stats, &Config::parse_from(args)).unwrap();
+
+    let compact = json(&["whoami", "--format", "json"]);
+    assert_eq!(compact.lines().count(), 1);
+    let pretty = json(&["whoami", "--format", "json", "--output", "stats.json"]);
+    assert!(pretty.contains("\n  \"username\": \"octocat\""), "{}", pretty);
+    assert_eq!(json(&["whoami", "--format", "json", "--json-pretty"]), pretty);
+    assert_eq!(json(&["whoami", "--format", "json", "--output", "stats.json", "--json-compact"]), compact);
+
+    let parsed: whoami::stats::Stats = serde_json::from_str(&compact).unwrap();
+    assert_eq!(parsed.total_stars, stats.total_stars);
+    assert!(Config::try_parse_from(["whoami", "--json-pretty", "--json-compact"]).is_err());
+}
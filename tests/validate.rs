@@ -0,0 +1,27 @@
+use std::fs;
+use whoami::validate::{validate_markdown, validate_svg};
+
+#[test]
+fn golden_outputs_validate() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/many_languages");
+    validate_markdown(&fs::read_to_string(format!("{}/README.md", dir)).unwrap()).unwrap();
+    validate_svg(&fs::read_to_string(format!("{}/stats.svg", dir)).unwrap()).unwrap();
+}
+
+#[test]
+fn markdown_problems_name_the_line() {
+    assert_eq!(
+        validate_markdown("# hi\n```\nfn main() {}\n").unwrap_err(),
+        "line 2: code fence is never closed"
+    );
+    assert_eq!(
+        validate_markdown("# hi\n- **{{ stars }}** stars\n").unwrap_err(),
+        "line 2: unrendered template syntax `{{`"
+    );
+}
+
+#[test]
+fn malformed_svg_is_rejected() {
+    let err = validate_svg(r#"<svg xmlns="http://www.w3.org/2000/svg"><text>A & B</text></svg>"#).unwrap_err();
+    assert!(err.starts_with("invalid SVG"), "{}", err);
+}